@@ -1,5 +1,6 @@
 use bridgerpay_connector::rest::api_client::{CheckoutWidgetType, RestApiClient, RestApiConfig};
 use bridgerpay_connector::rest::{ApplePayModel, CreateCashierSessionRequest};
+use bridgerpay_connector::money::Amount;
 use bridgerpay_connector::{generate_sign, CheckoutPayloadModel, CheckoutSign};
 use std::collections::HashMap;
 use std::time::Duration;
@@ -20,22 +21,17 @@ async fn main() {
 
 pub fn create_cashier_session_req() -> CreateCashierSessionRequest {
     let order_id = Uuid::new_v4().to_string();
-    let amount = 10.0;
+    let amount: Amount = "10.00".parse().unwrap();
     let currency = "USD".to_string();
 
-    CreateCashierSessionRequest {
-        cashier_key: None,
-        order_id: order_id.clone(),
-        currency: currency.clone(),
-        country: "NL".to_string(),
-        amount: Some(amount),
-        theme: None,
-        first_name: Some(String::from("John Smith")),
-        last_name: Some(String::from("Doe")),
-        phone: Some("38506466464".to_string()),
-        email: Some("test1234@mailinator.com".to_string()),
-        zip_code: Some(String::from("1718 AZ")),
-        payload: Some(
+    CreateCashierSessionRequest::builder(order_id.clone(), currency.clone(), "NL")
+        .amount(amount)
+        .first_name("John Smith")
+        .last_name("Doe")
+        .phone("38506466464")
+        .email("test1234@mailinator.com")
+        .zip_code("1718 AZ")
+        .payload(
             CheckoutPayloadModel {
                 timestamp: 123,
                 client_id: "test-client-id".to_string(),
@@ -52,25 +48,16 @@ pub fn create_cashier_session_req() -> CreateCashierSessionRequest {
                 order_id,
             }
             .encrypt(&std::env::var("API_KEY").unwrap()),
-        ),
-        currency_lock: Some(true),
-        amount_lock: Some(true),
-        platform_id: None,
-        tracking_id: None,
-        affiliate_id: None,
-        city: Some("Hoogwoud".to_string()),
-        address: Some("Boenluif 30".to_string()),
-        state: None,
-        hide_languages_dropdown: None,
-        language: None,
-        //apple_pay: None,
-        apple_pay: Some(ApplePayModel {
+        )
+        .currency_lock(true)
+        .amount_lock(true)
+        .city("Hoogwoud")
+        .address("Boenluif 30")
+        .apple_pay(ApplePayModel {
             shipping_contact_required: Some(true),
-        }),
-        button_text: None,
-        deposit_button_text: None,
-        pay_mode: None,
-    }
+        })
+        .build()
+        .unwrap()
 }
 
 pub async fn login(rest_client: &RestApiClient<ExampleApiConfig>) {
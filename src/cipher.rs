@@ -1,8 +1,20 @@
+use crate::keyring::Keyring;
 use base64::{engine::general_purpose, Engine};
 use prost::Message;
 use libaes::Cipher;
+use ring::aead;
+use ring::rand::{SecureRandom, SystemRandom};
 use sha2::{Digest, Sha512};
 
+/// First byte of a [`AeadCipher`] envelope. Legacy [`AesCipher`] data starts with the first byte
+/// of the protobuf message, a field tag, which is never `0x01` or `0x02` as field numbers start
+/// from 1.
+pub const AES_256_GCM_VERSION: u8 = 0x01;
+/// First byte of a [`AeadCipher`] envelope with the id of the key, see [`Keyring`].
+pub const KEYED_AES_256_GCM_VERSION: u8 = 0x02;
+
+/// Encrypts messages as base64 of [`AeadCipher`] envelopes. Decrypts them and the legacy
+/// [`AesCipher`] format.
 pub struct MessageCipher;
 
 impl MessageCipher {
@@ -44,7 +56,7 @@ impl MessageCipher {
     pub fn encrypt<T: Message>(src: &T, key: &str) -> String {
         let mut prost_encoded = Vec::new();
         Message::encode(src, &mut prost_encoded).expect("Failed to encode");
-        let data = AesCipher::encrypt(&prost_encoded, key);
+        let data = AeadCipher::encrypt(&prost_encoded, key);
         let base64_encoded = &general_purpose::STANDARD.encode(data);
 
         base64_encoded.to_owned()
@@ -58,7 +70,7 @@ impl MessageCipher {
         }
 
         let base64_decoded = base64_decoded.as_ref().unwrap();
-        let decrypted = AesCipher::decrypt(base64_decoded, key);
+        let decrypted = Self::decrypt_bytes(base64_decoded, key);
 
         let Ok(decrypted) = decrypted else {
             return Err(decrypted.unwrap_err());
@@ -71,8 +83,62 @@ impl MessageCipher {
             Ok(data) => Ok(data),
         }
     }
+
+    /// Encrypts with the active key of the keyring and embeds its id.
+    pub fn encrypt_with_keyring<T: Message>(src: &T, keyring: &Keyring) -> String {
+        let mut prost_encoded = Vec::new();
+        Message::encode(src, &mut prost_encoded).expect("Failed to encode");
+        let (key_id, key) = keyring.get_active_key();
+        let data = AeadCipher::encrypt_with_key_id(&prost_encoded, key_id, key);
+
+        general_purpose::STANDARD.encode(data)
+    }
+
+    /// Decrypts with the key referenced by the envelope. Envelopes without a key id, i.e. created
+    /// before the keyring, are tried with every key that is not retired, the active one first.
+    pub fn decrypt_with_keyring<T: Message + Default>(
+        src: &str,
+        keyring: &Keyring,
+    ) -> Result<T, String> {
+        let base64_decoded = general_purpose::STANDARD
+            .decode(src)
+            .map_err(|err| format!("{}", err))?;
+        let mut error = "No key to decrypt with".to_string();
+
+        for key in keyring.get_decryption_keys(&base64_decoded)? {
+            let decrypted = match Self::decrypt_bytes(&base64_decoded, key) {
+                // legacy data decrypted with a wrong key fails on padding and comes back empty
+                Ok(decrypted) if decrypted.is_empty() => {
+                    error = "Failed to decrypt".to_string();
+                    continue;
+                }
+                Ok(decrypted) => decrypted,
+                Err(err) => {
+                    error = err;
+                    continue;
+                }
+            };
+
+            match Message::decode(&decrypted[..]) {
+                Ok(data) => return Ok(data),
+                Err(err) => error = format!("{}", err),
+            }
+        }
+
+        Err(error)
+    }
+
+    fn decrypt_bytes(src: &[u8], key: &str) -> Result<Vec<u8>, String> {
+        match src.first() {
+            Some(&AES_256_GCM_VERSION) => AeadCipher::decrypt(src, key),
+            Some(&KEYED_AES_256_GCM_VERSION) => AeadCipher::decrypt_with_key_id(src, key),
+            _ => AesCipher::decrypt(src, key),
+        }
+    }
 }
 
+/// AES-192-CBC without authentication, prefixed with the IV. Kept to decrypt payloads created
+/// before [`AeadCipher`].
 pub struct AesCipher;
 
 impl AesCipher {
@@ -106,6 +172,8 @@ impl AesCipher {
         Ok(decrypted)
     }
 
+    /// The IV is the first 16 bytes of `src`, zero padded, so the data starts with the first byte
+    /// of `src`. [`MessageCipher::decrypt`] depends on it to tell the formats apart.
     pub fn encrypt(src: &[u8], key: &str) -> Vec<u8> {
         let mut hasher = Sha512::new();
         hasher.update(key);
@@ -114,7 +182,8 @@ impl AesCipher {
         aes_key.copy_from_slice(&key_hash[..24]);
 
         let mut iv = vec![0u8; 16];
-        iv.copy_from_slice(&src[..16]);
+        let iv_len = src.len().min(iv.len());
+        iv[..iv_len].copy_from_slice(&src[..iv_len]);
 
         let cipher = Cipher::new_192(&aes_key);
         let mut encrypted = cipher.cbc_encrypt(&iv, src);
@@ -155,3 +224,137 @@ impl AesCipher {
     }
 }
 
+
+/// AES-256-GCM envelope: version byte, random 12 byte nonce, then the ciphertext and the tag.
+/// [`KEYED_AES_256_GCM_VERSION`] envelopes have the key id length byte and the key id after the
+/// version byte. Everything before the nonce is authenticated too.
+pub struct AeadCipher;
+
+impl AeadCipher {
+    pub fn encrypt(src: &[u8], key: &str) -> Vec<u8> {
+        Self::seal(vec![AES_256_GCM_VERSION], src, key)
+    }
+
+    pub fn decrypt(src: &[u8], key: &str) -> Result<Vec<u8>, String> {
+        if src.first() != Some(&AES_256_GCM_VERSION) {
+            return Err("Unsupported version".to_string());
+        }
+
+        Self::open(src, 1, key)
+    }
+
+    /// Panics if the key id is longer than 255 bytes, see [`Keyring`] for a checked one.
+    pub fn encrypt_with_key_id(src: &[u8], key_id: &str, key: &str) -> Vec<u8> {
+        let key_id_len = u8::try_from(key_id.len()).expect("Key id is longer than 255 bytes");
+        let mut header = vec![KEYED_AES_256_GCM_VERSION, key_id_len];
+        header.extend_from_slice(key_id.as_bytes());
+
+        Self::seal(header, src, key)
+    }
+
+    pub fn decrypt_with_key_id(src: &[u8], key: &str) -> Result<Vec<u8>, String> {
+        let Some(key_id) = Self::get_key_id(src) else {
+            return Err("Unsupported version or malformed key id".to_string());
+        };
+
+        Self::open(src, 2 + key_id.len(), key)
+    }
+
+    /// Key id of a [`KEYED_AES_256_GCM_VERSION`] envelope.
+    pub fn get_key_id(src: &[u8]) -> Option<&str> {
+        if src.first() != Some(&KEYED_AES_256_GCM_VERSION) {
+            return None;
+        }
+
+        let key_id_len = *src.get(1)? as usize;
+        let key_id = src.get(2..2 + key_id_len)?;
+
+        std::str::from_utf8(key_id).ok()
+    }
+
+    fn seal(mut header: Vec<u8>, src: &[u8], key: &str) -> Vec<u8> {
+        let key = Self::get_key(key);
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .expect("Failed to generate nonce");
+
+        let mut encrypted = src.to_vec();
+        key.seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(&header),
+            &mut encrypted,
+        )
+        .expect("Failed to encrypt");
+
+        header.extend_from_slice(&nonce);
+        header.append(&mut encrypted);
+
+        header
+    }
+
+    fn open(src: &[u8], header_len: usize, key: &str) -> Result<Vec<u8>, String> {
+        let min_len = header_len + aead::NONCE_LEN + aead::AES_256_GCM.tag_len();
+
+        if src.len() < min_len {
+            return Err(format!("Src array len can't be less than {}", min_len));
+        }
+
+        let (header, data) = src.split_at(header_len);
+        let (nonce, encrypted) = data.split_at(aead::NONCE_LEN);
+        let nonce = aead::Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| "Invalid nonce".to_string())?;
+        let mut decrypted = encrypted.to_vec();
+        let len = Self::get_key(key)
+            .open_in_place(nonce, aead::Aad::from(header), &mut decrypted)
+            .map_err(|_| "Failed to decrypt: wrong key or tampered data".to_string())?
+            .len();
+        decrypted.truncate(len);
+
+        Ok(decrypted)
+    }
+
+    fn get_key(key: &str) -> aead::LessSafeKey {
+        let mut hasher = Sha512::new();
+        hasher.update(key);
+        let key_hash = hasher.finalize();
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key_hash[..32])
+            .expect("AES-256 key is 32 bytes");
+
+        aead::LessSafeKey::new(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AesCipher, MessageCipher};
+    use crate::CheckoutPayloadModel;
+    use base64::{engine::general_purpose, Engine};
+
+    #[test]
+    fn decrypts_versioned_and_legacy_payloads() {
+        let model = CheckoutPayloadModel {
+            order_id: "order-1".to_string(),
+            ..Default::default()
+        };
+
+        let encrypted = MessageCipher::encrypt(&model, "key");
+        let decrypted: CheckoutPayloadModel = MessageCipher::decrypt(&encrypted, "key").unwrap();
+        assert_eq!(decrypted, model);
+        assert!(MessageCipher::decrypt::<CheckoutPayloadModel>(&encrypted, "other").is_err());
+
+        let mut tampered = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let tampered = general_purpose::STANDARD.encode(tampered);
+        assert!(MessageCipher::decrypt::<CheckoutPayloadModel>(&tampered, "key").is_err());
+
+        let iv = b"\x2a\x07order-1\0\0\0\0\0\0\0";
+        let mut legacy = iv.to_vec();
+        legacy.append(&mut AesCipher::encrypt_with_iv(b"\x2a\x07order-1", "key", iv));
+        assert_eq!(legacy, AesCipher::encrypt(b"\x2a\x07order-1", "key"));
+        let legacy = general_purpose::STANDARD.encode(legacy);
+        let decrypted: CheckoutPayloadModel = MessageCipher::decrypt(&legacy, "key").unwrap();
+        assert_eq!(decrypted, model);
+    }
+}
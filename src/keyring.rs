@@ -0,0 +1,164 @@
+use crate::cipher::AeadCipher;
+use crate::get_now;
+use std::time::Duration;
+
+/// Encryption keys by id for key rotation. Payloads are encrypted with the active key and embed
+/// its id, older keys only decrypt payloads created before the rotation until they are retired.
+#[derive(Clone)]
+pub struct Keyring {
+    active_key_id: String,
+    keys: Vec<KeyringKey>,
+}
+
+#[derive(Clone)]
+struct KeyringKey {
+    id: String,
+    key: String,
+    /// Unix time in seconds the key stops decrypting at.
+    retired_at: Option<u64>,
+}
+
+impl KeyringKey {
+    fn is_usable(&self, now: u64) -> bool {
+        match self.retired_at {
+            Some(retired_at) => retired_at > now,
+            None => true,
+        }
+    }
+}
+
+impl Keyring {
+    /// Key ids are embedded in the payloads, so they have to be 1 to 255 bytes long.
+    pub fn new(key_id: impl Into<String>, key: impl Into<String>) -> Result<Self, String> {
+        let mut keyring = Self {
+            active_key_id: String::new(),
+            keys: Vec::new(),
+        };
+        keyring.rotate(key_id, key)?;
+
+        Ok(keyring)
+    }
+
+    /// Adds a key that only decrypts, e.g. the previous key after a restart.
+    pub fn add_key(
+        &mut self,
+        key_id: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Result<(), String> {
+        let key_id = key_id.into();
+
+        if key_id.is_empty() || key_id.len() > u8::MAX as usize {
+            return Err(format!("Key id {key_id} must be 1 to 255 bytes long"));
+        }
+
+        if self.keys.iter().any(|key| key.id == key_id) {
+            return Err(format!("Key id {key_id} already exists"));
+        }
+
+        self.keys.push(KeyringKey {
+            id: key_id,
+            key: key.into(),
+            retired_at: None,
+        });
+
+        Ok(())
+    }
+
+    /// Adds the key and encrypts with it from now on. The previous active key keeps decrypting
+    /// until it is retired.
+    pub fn rotate(
+        &mut self,
+        key_id: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Result<(), String> {
+        let key_id = key_id.into();
+        self.add_key(key_id.clone(), key)?;
+        self.active_key_id = key_id;
+
+        Ok(())
+    }
+
+    /// Stops decrypting with the key after the grace period, it should be longer than the
+    /// lifetime of a cashier session. The active key can't be retired.
+    pub fn retire(&mut self, key_id: &str, grace_period: Duration) -> Result<(), String> {
+        if key_id == self.active_key_id {
+            return Err(format!("Key id {key_id} is active"));
+        }
+
+        let Some(key) = self.keys.iter_mut().find(|key| key.id == key_id) else {
+            return Err(format!("Unknown key id {key_id}"));
+        };
+
+        key.retired_at = Some(get_now() + grace_period.as_secs());
+
+        Ok(())
+    }
+
+    pub fn get_active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Id and key to encrypt with.
+    pub fn get_active_key(&self) -> (&str, &str) {
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.id == self.active_key_id)
+            .expect("Active key is in the keyring");
+
+        (&key.id, &key.key)
+    }
+
+    /// Keys to try for the encrypted data: the one of its key id or, for data without a key id,
+    /// every key that is not retired, the active one first.
+    pub fn get_decryption_keys(&self, encrypted: &[u8]) -> Result<Vec<&str>, String> {
+        let now = get_now();
+        let mut keys: Vec<_> = self.keys.iter().filter(|key| key.is_usable(now)).collect();
+
+        if let Some(key_id) = AeadCipher::get_key_id(encrypted) {
+            return match keys.into_iter().find(|key| key.id == key_id) {
+                Some(key) => Ok(vec![key.key.as_str()]),
+                None => Err(format!("Unknown or retired key id {key_id}")),
+            };
+        }
+
+        keys.sort_by_key(|key| key.id != self.active_key_id);
+
+        Ok(keys.into_iter().map(|key| key.key.as_str()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keyring;
+    use crate::cipher::MessageCipher;
+    use crate::CheckoutPayloadModel;
+    use std::time::Duration;
+
+    #[test]
+    fn decrypts_with_rotated_keys_until_retired() {
+        let model = CheckoutPayloadModel {
+            order_id: "order-1".to_string(),
+            ..Default::default()
+        };
+        let mut keyring = Keyring::new("2024-01", "old-key").unwrap();
+        let old = model.encrypt_with_keyring(&keyring);
+        let unkeyed = model.encrypt("old-key");
+
+        keyring.rotate("2024-02", "new-key").unwrap();
+        let new = model.encrypt_with_keyring(&keyring);
+        let decrypted = MessageCipher::decrypt::<CheckoutPayloadModel>(&new, "new-key");
+        assert_eq!(decrypted.unwrap(), model);
+
+        for payload in [&old, &unkeyed, &new] {
+            let decrypted = CheckoutPayloadModel::try_decrypt_with_keyring(payload, &keyring);
+            assert_eq!(decrypted.unwrap(), model);
+        }
+
+        assert!(keyring.retire("2024-02", Duration::ZERO).is_err());
+        keyring.retire("2024-01", Duration::ZERO).unwrap();
+        assert!(CheckoutPayloadModel::try_decrypt_with_keyring(&old, &keyring).is_err());
+        assert!(CheckoutPayloadModel::try_decrypt_with_keyring(&unkeyed, &keyring).is_err());
+        assert!(CheckoutPayloadModel::try_decrypt_with_keyring(&new, &keyring).is_ok());
+    }
+}
@@ -1,11 +1,19 @@
 use crate::cipher::MessageCipher;
+use crate::keyring::Keyring;
+use crate::money::Amount;
+use crate::webhook::WebhookPayload;
 use base64::engine::general_purpose;
 use base64::Engine;
 use ring::hmac;
 use serde::{Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub mod cipher;
+pub mod iso;
+pub mod keyring;
+pub mod money;
 pub mod rest;
 pub mod webhook;
 
@@ -31,11 +39,158 @@ impl CheckoutPayloadModel {
     pub fn try_decrypt(str: &str, key: &str) -> Result<CheckoutPayloadModel, String> {
         MessageCipher::decrypt(str, key)
     }
+
+    pub fn encrypt_with_keyring(&self, keyring: &Keyring) -> String {
+        MessageCipher::encrypt_with_keyring(self, keyring)
+    }
+
+    pub fn try_decrypt_with_keyring(
+        str: &str,
+        keyring: &Keyring,
+    ) -> Result<CheckoutPayloadModel, String> {
+        MessageCipher::decrypt_with_keyring(str, keyring)
+    }
+
+    /// Decrypts `meta.payload` of the webhook and checks that its `sign` matches the charge of
+    /// the webhook and its `timestamp`, unix time in seconds, is not older than `max_age`.
+    pub fn verify_webhook(
+        webhook: &WebhookPayload,
+        encryption_key: &str,
+        sign_key: &str,
+        max_age: Duration,
+    ) -> Result<CheckoutPayloadModel, CheckoutPayloadError> {
+        Self::verify_webhook_at(webhook, encryption_key, sign_key, max_age, get_now())
+    }
+
+    /// Same as [`Self::verify_webhook`] at `now`, unix time in seconds.
+    pub fn verify_webhook_at(
+        webhook: &WebhookPayload,
+        encryption_key: &str,
+        sign_key: &str,
+        max_age: Duration,
+        now: u64,
+    ) -> Result<CheckoutPayloadModel, CheckoutPayloadError> {
+        let decrypt = |payload: &str| Self::try_decrypt(payload, encryption_key);
+
+        Self::verify_decrypted_webhook(webhook, decrypt, sign_key, max_age, now)
+    }
+
+    /// Same as [`Self::verify_webhook`] with the payload decrypted by the keyring.
+    pub fn verify_webhook_with_keyring(
+        webhook: &WebhookPayload,
+        keyring: &Keyring,
+        sign_key: &str,
+        max_age: Duration,
+    ) -> Result<CheckoutPayloadModel, CheckoutPayloadError> {
+        Self::verify_webhook_with_keyring_at(webhook, keyring, sign_key, max_age, get_now())
+    }
+
+    /// Same as [`Self::verify_webhook_with_keyring`] at `now`, unix time in seconds.
+    pub fn verify_webhook_with_keyring_at(
+        webhook: &WebhookPayload,
+        keyring: &Keyring,
+        sign_key: &str,
+        max_age: Duration,
+        now: u64,
+    ) -> Result<CheckoutPayloadModel, CheckoutPayloadError> {
+        let decrypt = |payload: &str| Self::try_decrypt_with_keyring(payload, keyring);
+
+        Self::verify_decrypted_webhook(webhook, decrypt, sign_key, max_age, now)
+    }
+
+    fn verify_decrypted_webhook(
+        webhook: &WebhookPayload,
+        decrypt: impl FnOnce(&str) -> Result<CheckoutPayloadModel, String>,
+        sign_key: &str,
+        max_age: Duration,
+        now: u64,
+    ) -> Result<CheckoutPayloadModel, CheckoutPayloadError> {
+        let Some(payload) = &webhook.meta.payload else {
+            return Err(CheckoutPayloadError::MissingPayload);
+        };
+
+        let model = decrypt(payload).map_err(CheckoutPayloadError::MalformedPayload)?;
+
+        if model.order_id != webhook.data.order_id {
+            return Err(CheckoutPayloadError::OrderMismatch {
+                expected: model.order_id,
+                actual: webhook.data.order_id.clone(),
+            });
+        }
+
+        let attributes = webhook.data.charge.as_ref().map(|charge| &charge.attributes);
+        let (Some(amount), Some(currency)) = (
+            attributes.and_then(|attributes| attributes.amount),
+            attributes.and_then(|attributes| attributes.currency.clone()),
+        ) else {
+            return Err(CheckoutPayloadError::MissingCharge);
+        };
+
+        let sign = CheckoutSign {
+            amount,
+            order_id: webhook.data.order_id.clone(),
+            currency,
+        };
+
+        if !verify_sign(&sign, &model.sign, sign_key) {
+            return Err(CheckoutPayloadError::InvalidSign);
+        }
+
+        let age = (now as i64).saturating_sub(model.timestamp).unsigned_abs();
+
+        // a timestamp in the future is rejected the same way, within the clock skew of max_age
+        if age > max_age.as_secs() {
+            return Err(CheckoutPayloadError::Expired {
+                timestamp: model.timestamp,
+                now,
+            });
+        }
+
+        Ok(model)
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckoutPayloadError {
+    /// The webhook has no `meta.payload`.
+    MissingPayload,
+    /// The payload can't be decrypted or decoded.
+    MalformedPayload(String),
+    /// The webhook has no charge with an amount and currency to check the sign against.
+    MissingCharge,
+    /// The payload was created for another order.
+    OrderMismatch { expected: String, actual: String },
+    /// The sign doesn't match the amount, currency or order of the charge.
+    InvalidSign,
+    Expired { timestamp: i64, now: u64 },
+}
+
+impl fmt::Display for CheckoutPayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckoutPayloadError::MissingPayload => write!(f, "Webhook has no checkout payload"),
+            CheckoutPayloadError::MalformedPayload(err) => {
+                write!(f, "Malformed checkout payload: {err}")
+            }
+            CheckoutPayloadError::MissingCharge => {
+                write!(f, "Webhook has no charge amount and currency")
+            }
+            CheckoutPayloadError::OrderMismatch { expected, actual } => {
+                write!(f, "Checkout payload of order {expected} in webhook of order {actual}")
+            }
+            CheckoutPayloadError::InvalidSign => write!(f, "Invalid checkout payload sign"),
+            CheckoutPayloadError::Expired { timestamp, now } => {
+                write!(f, "Checkout payload created at {timestamp} expired at {now}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckoutPayloadError {}
+
 #[derive(Clone, Serialize)]
 pub struct CheckoutSign {
-    pub amount: f64,
+    pub amount: Amount,
     pub order_id: String,
     pub currency: String,
 }
@@ -56,3 +211,101 @@ fn sign_str(str: &str, key: &str) -> String {
 
     general_purpose::STANDARD.encode(signature)
 }
+
+pub(crate) fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+/// Checks a sign of [`generate_sign`] in constant time.
+pub fn verify_sign<T: Serialize>(data: &T, sign: &str, key: &str) -> bool {
+    let Ok(data) = serde_json::to_string(data) else {
+        return false;
+    };
+    let Ok(sign) = general_purpose::STANDARD.decode(sign) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA512, key.as_bytes());
+
+    hmac::verify(&key, data.as_bytes(), &sign).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyring::Keyring;
+    use crate::webhook::test_support::webhook_json;
+    use crate::webhook::WebhookPayload;
+    use crate::{generate_sign, CheckoutPayloadError, CheckoutPayloadModel, CheckoutSign};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn webhook(order_id: &str, amount: f64, payload: &str) -> WebhookPayload {
+        let charge = serde_json::json!({
+            "type": "charge",
+            "attributes": {
+                "status": "approved",
+                "amount": amount,
+                "currency": "USD",
+                "created_at": 1_000,
+            },
+        });
+        let mut webhook = webhook_json("approved", charge);
+        webhook["data"]["order_id"] = order_id.into();
+        webhook["meta"]["payload"] = payload.into();
+
+        serde_json::from_value(webhook).unwrap()
+    }
+
+    #[test]
+    fn verifies_checkout_payload_of_webhook() {
+        let sign = CheckoutSign {
+            amount: "10.5".parse().unwrap(),
+            order_id: "order-1".to_string(),
+            currency: "USD".to_string(),
+        };
+        let model = CheckoutPayloadModel {
+            timestamp: 900,
+            client_id: "client-1".to_string(),
+            sign: generate_sign(&sign, "sign-key").unwrap(),
+            metadata: HashMap::new(),
+            order_id: "order-1".to_string(),
+        };
+        let payload = model.encrypt("encryption-key");
+        let max_age = Duration::from_secs(300);
+        let verify = |webhook: &WebhookPayload, now| {
+            CheckoutPayloadModel::verify_webhook_at(
+                webhook,
+                "encryption-key",
+                "sign-key",
+                max_age,
+                now,
+            )
+        };
+
+        let model = verify(&webhook("order-1", 10.5, &payload), 1_000).unwrap();
+        assert_eq!(model.client_id, "client-1");
+
+        assert_eq!(
+            verify(&webhook("order-1", 1.5, &payload), 1_000),
+            Err(CheckoutPayloadError::InvalidSign)
+        );
+        assert!(matches!(
+            verify(&webhook("order-2", 10.5, &payload), 1_000),
+            Err(CheckoutPayloadError::OrderMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(&webhook("order-1", 10.5, &payload), 1_300),
+            Err(CheckoutPayloadError::Expired { .. })
+        ));
+
+        let keyring = Keyring::new("key-1", "encryption-key").unwrap();
+        let payload = model.encrypt_with_keyring(&keyring);
+        let webhook = webhook("order-1", 10.5, &payload);
+        let result = CheckoutPayloadModel::verify_webhook_with_keyring_at(
+            &webhook, &keyring, "sign-key", max_age, 1_000,
+        );
+        assert_eq!(result, Ok(model));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::iso::get_currency_exponent;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_SCALE: u32 = 18;
+
+/// Exact decimal amount equal to `units / 10^scale`.
+///
+/// Serialized as a JSON number the same way `f64` is, so BridgerPay payloads and signatures
+/// built with [`crate::generate_sign`] don't change, but the value never goes through float
+/// arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Amount {
+    units: i64,
+    scale: u32,
+}
+
+impl Amount {
+    pub const ZERO: Amount = Amount { units: 0, scale: 0 };
+
+    pub fn new(units: i64, scale: u32) -> Option<Self> {
+        if scale > MAX_SCALE {
+            return None;
+        }
+
+        Some(Self { units, scale }.normalize())
+    }
+
+    /// Amount from an integer number of currency minor units, e.g. cents for USD.
+    pub fn from_minor_units(minor_units: i64, currency: &str) -> Option<Self> {
+        Self::new(minor_units, get_currency_exponent(currency)?)
+    }
+
+    /// Integer number of currency minor units. `None` for unknown currencies and amounts
+    /// with more decimal places than the currency allows.
+    pub fn to_minor_units(&self, currency: &str) -> Option<i64> {
+        let exponent = get_currency_exponent(currency)?;
+
+        if self.scale > exponent {
+            return None;
+        }
+
+        self.units.checked_mul(10_i64.pow(exponent - self.scale))
+    }
+
+    pub fn get_units(&self) -> i64 {
+        self.units
+    }
+
+    pub fn get_scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.units < 0
+    }
+
+    pub fn checked_add(&self, other: Amount) -> Option<Amount> {
+        let scale = self.scale.max(other.scale);
+        let units = self.rescale(scale)?.checked_add(other.rescale(scale)?)?;
+
+        Amount::new(units, scale)
+    }
+
+    pub fn checked_sub(&self, other: Amount) -> Option<Amount> {
+        let scale = self.scale.max(other.scale);
+        let units = self.rescale(scale)?.checked_sub(other.rescale(scale)?)?;
+
+        Amount::new(units, scale)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        // parsing the decimal string gives the closest f64, unlike dividing by a power of ten
+        self.to_string().parse().unwrap()
+    }
+
+    fn rescale(&self, scale: u32) -> Option<i64> {
+        self.units.checked_mul(10_i64.checked_pow(scale - self.scale)?)
+    }
+
+    fn normalize(mut self) -> Self {
+        while self.scale > 0 && self.units % 10 == 0 {
+            self.units /= 10;
+            self.scale -= 1;
+        }
+
+        if self.units == 0 {
+            self.scale = 0;
+        }
+
+        self
+    }
+}
+
+impl Ord for Amount {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        let left = self.units as i128 * 10_i128.pow(scale - self.scale);
+        let right = other.units as i128 * 10_i128.pow(scale - other.scale);
+
+        left.cmp(&right)
+    }
+}
+
+impl PartialOrd for Amount {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let units = self.units.unsigned_abs();
+
+        if self.scale == 0 {
+            return write!(f, "{sign}{units}");
+        }
+
+        let divisor = 10_u64.pow(self.scale);
+        let fraction = units % divisor;
+        write!(
+            f,
+            "{sign}{}.{fraction:0width$}",
+            units / divisor,
+            width = self.scale as usize
+        )
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid amount {value:?}");
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if integer.is_empty()
+            || !integer.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(error());
+        }
+
+        let fraction = fraction.trim_end_matches('0');
+        let scale = fraction.len() as u32;
+
+        if scale > MAX_SCALE {
+            return Err(error());
+        }
+
+        let units: i64 = format!("{integer}{fraction}").parse().map_err(|_| error())?;
+        let units = if negative { -units } else { units };
+
+        Amount::new(units, scale).ok_or_else(error)
+    }
+}
+
+impl TryFrom<f64> for Amount {
+    type Error = String;
+
+    /// Takes the shortest decimal that converts back to the same `f64`, e.g. `10.15` and not
+    /// `10.1500000000000003552713678800500929355621337890625`.
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if !value.is_finite() {
+            return Err(format!("Invalid amount {value}"));
+        }
+
+        value.to_string().parse()
+    }
+}
+
+impl From<i64> for Amount {
+    fn from(value: i64) -> Self {
+        Amount {
+            units: value,
+            scale: 0,
+        }
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+struct AmountVisitor;
+
+impl Visitor<'_> for AmountVisitor {
+    type Value = Amount;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal amount")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Amount, E> {
+        Ok(Amount::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Amount, E> {
+        i64::try_from(value)
+            .map(Amount::from)
+            .map_err(|_| E::custom(format!("Invalid amount {value}")))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Amount, E> {
+        Amount::try_from(value).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Amount, E> {
+        value.parse().map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Amount;
+
+    #[test]
+    fn parses_and_formats_exactly() {
+        let amount: Amount = "10.150".parse().unwrap();
+
+        assert_eq!(amount.to_string(), "10.15");
+        assert_eq!(amount.to_minor_units("USD"), Some(1015));
+        assert_eq!(amount.to_minor_units("JPY"), None);
+        assert_eq!(amount.to_minor_units("KWD"), Some(10150));
+        assert_eq!("-0.05".parse::<Amount>().unwrap().to_string(), "-0.05");
+        assert!("1.2.3".parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn serializes_like_f64() {
+        let amount = Amount::from_minor_units(1000, "USD").unwrap();
+
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "10.0");
+        assert_eq!(
+            serde_json::to_string(&"0.1".parse::<Amount>().unwrap()).unwrap(),
+            serde_json::to_string(&0.1f64).unwrap()
+        );
+
+        let amount: Amount = serde_json::from_str("0.29").unwrap();
+        assert_eq!(amount.to_minor_units("EUR"), Some(29));
+    }
+
+    #[test]
+    fn compares_and_adds_across_scales() {
+        let a: Amount = "0.1".parse().unwrap();
+        let b: Amount = "0.2".parse().unwrap();
+
+        assert_eq!(a.checked_add(b), Some("0.3".parse().unwrap()));
+        assert!(Amount::from(1) > "0.99".parse().unwrap());
+        assert_eq!(Amount::from(1), "1.00".parse().unwrap());
+    }
+}
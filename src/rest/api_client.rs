@@ -1,16 +1,31 @@
+use crate::money::Amount;
+use crate::rest::credentials::CredentialCache;
 use crate::rest::endpoints::RestApiEndpoint;
 use crate::rest::errors::Error;
+use crate::rest::retry::RetryPolicy;
+use crate::rest::transport::{
+    FlUrlTransport, HttpRequest, HttpResponse, HttpTransport, TransportError,
+};
+use crate::rest::validation::validate_refund;
 use crate::rest::{
-    CashierSessionModel, CreateCashierSessionRequest, LoginModel, LoginRequest, Response,
+    CaptureRequest, CashierSessionModel, CashierSessionStatusModel, ChargeTokenRequest,
+    CreateCashierSessionRequest, CreatePaymentLinkRequest, CreatePayoutRequest,
+    ListTransactionsRequest, LoginModel, LoginRequest, PaymentLinkModel, PayoutModel,
+    RefreshTokenRequest, RefundRequest, Response, TransactionModel, TransactionsPageModel,
+    VoidRequest,
 };
-use error_chain::bail;
-use flurl::{FlUrl, FlUrlResponse};
+use futures::stream::{self, Stream};
 use http::{Method, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::time::Duration;
 
+/// How long before the access token expiry the client refreshes it, at most half of the token
+/// lifetime.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
 const CHECKOUT_WIDGET_TEMPLATE: &str = "<html><body><script src='https://checkout.bridgerpay.com/v2/launcher' data-cashier-key='{{cashier_key}}' data-cashier-token='{{cashier_token}}'></script></body></html>";
 const WRAPPED_CHECKOUT_WIDGET_TEMPLATE: &str = r#"<!DOCTYPE html>
 <html>
@@ -55,46 +70,138 @@ fn get_wallet_checkout_widget_template() -> String {
 }
 
 #[async_trait::async_trait]
-pub trait RestApiConfig {
+pub trait RestApiConfig: Send + Sync {
     async fn get_api_url(&self) -> String;
     async fn get_api_key(&self) -> String;
     async fn get_timeout(&self) -> Duration;
     async fn get_user_name(&self) -> String;
     async fn get_password(&self) -> String;
     async fn get_cashier_key(&self) -> String;
+    async fn get_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
 }
 
-pub struct RestApiClient<C: RestApiConfig> {
+pub struct RestApiClient<C: RestApiConfig, H: HttpTransport = FlUrlTransport> {
     pub config: C,
-    login_result: std::sync::Mutex<Option<LoginModel>>,
+    transport: H,
+    credentials: CredentialCache,
 }
 
 impl<C: RestApiConfig> RestApiClient<C> {
     pub fn new(config: C) -> Self {
+        Self::with_transport(config, FlUrlTransport)
+    }
+}
+
+impl<C: RestApiConfig, H: HttpTransport> RestApiClient<C, H> {
+    pub fn with_transport(config: C, transport: H) -> Self {
         Self {
             config,
-            login_result: Default::default(),
+            transport,
+            credentials: Default::default(),
         }
     }
 
     pub async fn login(&self) -> Result<LoginModel, Error> {
+        let _renewal = self.credentials.lock_renewal().await;
+
+        self.login_unsynced().await
+    }
+
+    pub async fn refresh_token(&self) -> Result<LoginModel, Error> {
+        let _renewal = self.credentials.lock_renewal().await;
+
+        self.refresh_token_unsynced().await
+    }
+
+    pub async fn is_logged_in(&self) -> Result<bool, Error> {
+        Ok(self.credentials.get().is_some())
+    }
+
+    async fn login_unsynced(&self) -> Result<LoginModel, Error> {
         let endpoint = RestApiEndpoint::AuthLogin;
         let request = LoginRequest {
             user_name: self.config.get_user_name().await,
             password: self.config.get_password().await,
         };
         let resp: LoginModel = self
-            .send_deserialized(endpoint, Some(&request), None)
+            .send_with_retries(endpoint, Some(&request), None)
             .await?;
+        self.credentials.set(resp.clone());
+
+        Ok(resp)
+    }
 
-        let mut access_token = self.login_result.lock().unwrap();
-        access_token.replace(resp.clone());
+    async fn refresh_token_unsynced(&self) -> Result<LoginModel, Error> {
+        let endpoint = RestApiEndpoint::AuthRefreshToken;
+
+        let Some(credentials) = self.credentials.get() else {
+            return Err(Error::NotLoggedIn);
+        };
+
+        let request = RefreshTokenRequest {
+            refresh_token: credentials.login.refresh_token,
+        };
+        let resp: LoginModel = self
+            .send_with_retries(endpoint, Some(&request), None)
+            .await?;
+        self.credentials.set(resp.clone());
 
         Ok(resp)
     }
 
-    pub async fn is_logged_in(&self) -> Result<bool, Error> {
-        Ok(self.login_result.lock().unwrap().is_some())
+    /// Refreshes the access token, falling back to a full login if the refresh fails.
+    /// Must be called while holding the renewal lock.
+    async fn renew_unsynced(&self) -> Result<LoginModel, Error> {
+        match self.refresh_token_unsynced().await {
+            Ok(resp) => Ok(resp),
+            Err(err) => {
+                if std::env::var("DEBUG").is_ok() {
+                    println!("refresh token failed, logging in: {}", err);
+                }
+
+                self.login_unsynced().await
+            }
+        }
+    }
+
+    /// Returns an access token that is not about to expire. Concurrent callers share
+    /// a single in-flight login or refresh.
+    async fn get_access_token(&self) -> Result<String, Error> {
+        if let Some(credentials) = self.credentials.get() {
+            if !credentials.is_expiring(TOKEN_REFRESH_MARGIN) {
+                return Ok(credentials.access_token().to_string());
+            }
+        }
+
+        let _renewal = self.credentials.lock_renewal().await;
+
+        // the token could have been renewed while waiting for the lock
+        let resp = match self.credentials.get() {
+            Some(credentials) if !credentials.is_expiring(TOKEN_REFRESH_MARGIN) => {
+                return Ok(credentials.access_token().to_string());
+            }
+            Some(_) => self.renew_unsynced().await?,
+            None => self.login_unsynced().await?,
+        };
+
+        Ok(resp.access_token.token)
+    }
+
+    /// Renews the token rejected by the server unless a concurrent caller already did.
+    async fn renew_rejected_token(&self, rejected_token: &str) -> Result<(), Error> {
+        let _renewal = self.credentials.lock_renewal().await;
+
+        if let Some(credentials) = self.credentials.get() {
+            if credentials.access_token() != rejected_token {
+                return Ok(());
+            }
+        }
+
+        self.renew_unsynced().await?;
+
+        Ok(())
     }
 
     pub async fn create_cashier_session(
@@ -108,6 +215,8 @@ impl<C: RestApiConfig> RestApiClient<C> {
             request.cashier_key = Some(self.config.get_cashier_key().await);
         }
 
+        request.validate()?;
+
         let resp: CashierSessionModel = self
             .send_deserialized(
                 endpoint,
@@ -119,16 +228,323 @@ impl<C: RestApiConfig> RestApiClient<C> {
         Ok(resp)
     }
 
+    /// Current status of the session created by [`Self::create_cashier_session`].
+    pub async fn get_cashier_session(
+        &self,
+        cashier_token: &str,
+    ) -> Result<CashierSessionStatusModel, Error> {
+        let endpoint = RestApiEndpoint::GetCashierSession;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: CashierSessionStatusModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{cashier_token}")))
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Closes an open session so no payment can be made in it anymore.
+    pub async fn cancel_cashier_session(
+        &self,
+        cashier_token: &str,
+    ) -> Result<CashierSessionStatusModel, Error> {
+        let endpoint = RestApiEndpoint::CancelCashierSession;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: CashierSessionStatusModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{cashier_token}")))
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Shareable url of a hosted checkout page, an alternative to the embedded widget.
+    pub async fn create_payment_link(
+        &self,
+        request: CreatePaymentLinkRequest,
+    ) -> Result<PaymentLinkModel, Error> {
+        let endpoint = RestApiEndpoint::CreatePaymentLink;
+        let mut request = request;
+
+        if request.session.cashier_key.is_none() {
+            request.session.cashier_key = Some(self.config.get_cashier_key().await);
+        }
+
+        request.validate()?;
+
+        let resp: PaymentLinkModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    pub async fn get_payment_link(&self, payment_link_id: &str) -> Result<PaymentLinkModel, Error> {
+        let endpoint = RestApiEndpoint::GetPaymentLink;
+        let api_key = self.config.get_api_key().await;
+        let path_params = format!("{api_key}/{payment_link_id}");
+
+        let resp: PaymentLinkModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&path_params))
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Expires the link before its expiry time so it can't be paid anymore.
+    pub async fn expire_payment_link(
+        &self,
+        payment_link_id: &str,
+    ) -> Result<PaymentLinkModel, Error> {
+        let endpoint = RestApiEndpoint::ExpirePaymentLink;
+        let api_key = self.config.get_api_key().await;
+        let path_params = format!("{api_key}/{payment_link_id}");
+
+        let resp: PaymentLinkModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&path_params))
+            .await?;
+
+        Ok(resp)
+    }
+
+    pub async fn get_transactions_by_order_id(
+        &self,
+        order_id: &str,
+    ) -> Result<Vec<TransactionModel>, Error> {
+        let endpoint = RestApiEndpoint::GetTransactionsByOrderId;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: Vec<TransactionModel> = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{order_id}")))
+            .await?;
+
+        Ok(resp)
+    }
+
+    pub async fn get_transaction_by_charge_id(
+        &self,
+        charge_id: &str,
+    ) -> Result<TransactionModel, Error> {
+        let endpoint = RestApiEndpoint::GetTransactionByChargeId;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: TransactionModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{charge_id}")))
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Single page of the transaction listing, the first one when `request.page` is not set.
+    pub async fn list_transactions_page(
+        &self,
+        request: &ListTransactionsRequest,
+    ) -> Result<TransactionsPageModel, Error> {
+        let endpoint = RestApiEndpoint::ListTransactions;
+
+        let resp: TransactionsPageModel = self
+            .send_deserialized(
+                endpoint,
+                Some(request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// All transactions matching the filters, starting from `request.page`. Next pages are
+    /// requested as the stream is consumed. The stream ends after the first error.
+    pub fn list_transactions(
+        &self,
+        request: ListTransactionsRequest,
+    ) -> impl Stream<Item = Result<TransactionModel, Error>> + '_ {
+        let state = ListTransactionsState {
+            request,
+            transactions: VecDeque::new(),
+            has_more: true,
+        };
+
+        stream::unfold(Some(state), move |state| async move {
+            let mut state = state?;
+
+            loop {
+                if let Some(transaction) = state.transactions.pop_front() {
+                    return Some((Ok(transaction), Some(state)));
+                }
+
+                if !state.has_more {
+                    return None;
+                }
+
+                match self.list_transactions_page(&state.request).await {
+                    Ok(page) => {
+                        state.has_more =
+                            page.page < page.total_pages && !page.transactions.is_empty();
+                        state.request.page = Some(page.page + 1);
+                        state.transactions = page.transactions.into();
+                    }
+                    Err(error) => return Some((Err(error), None)),
+                }
+            }
+        })
+    }
+
+    /// Refunds the charge fully or partially. Fails before sending when the refund would
+    /// exceed what is left of the charge after the previous refunds of the order.
+    pub async fn refund(
+        &self,
+        charge_id: &str,
+        amount: Option<Amount>,
+        reason: &str,
+    ) -> Result<TransactionModel, Error> {
+        let endpoint = RestApiEndpoint::Refund;
+        let original = self.get_transaction_by_charge_id(charge_id).await?;
+        let order_transactions = self
+            .get_transactions_by_order_id(&original.order_id)
+            .await?;
+        let amount = validate_refund(&original, &order_transactions, amount)?;
+        let request = RefundRequest {
+            charge_id: charge_id.to_string(),
+            amount: Some(amount),
+            reason: reason.to_string(),
+        };
+
+        let resp: TransactionModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Captures an authorized charge. Without `amount` the whole authorized amount is captured.
+    pub async fn capture(
+        &self,
+        charge_id: &str,
+        amount: Option<Amount>,
+    ) -> Result<TransactionModel, Error> {
+        let endpoint = RestApiEndpoint::Capture;
+        let request = CaptureRequest {
+            charge_id: charge_id.to_string(),
+            amount,
+        };
+
+        request.validate()?;
+
+        let resp: TransactionModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Releases the funds of an authorized charge that won't be captured.
+    pub async fn void(&self, charge_id: &str) -> Result<TransactionModel, Error> {
+        let endpoint = RestApiEndpoint::Void;
+        let request = VoidRequest {
+            charge_id: charge_id.to_string(),
+        };
+
+        let resp: TransactionModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Charges a stored card without the customer. The returned charge status is final unless
+    /// it is `approved_on_hold`.
+    pub async fn charge_token(
+        &self,
+        request: ChargeTokenRequest,
+    ) -> Result<TransactionModel, Error> {
+        let endpoint = RestApiEndpoint::ChargeToken;
+        let mut request = request;
+
+        if request.cashier_key.is_none() {
+            request.cashier_key = Some(self.config.get_cashier_key().await);
+        }
+
+        request.validate()?;
+
+        let resp: TransactionModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Sends funds to a stored card token or a bank account.
+    pub async fn create_payout(&self, request: CreatePayoutRequest) -> Result<PayoutModel, Error> {
+        let endpoint = RestApiEndpoint::CreatePayout;
+        let mut request = request;
+
+        if request.cashier_key.is_none() {
+            request.cashier_key = Some(self.config.get_cashier_key().await);
+        }
+
+        request.validate()?;
+
+        let resp: PayoutModel = self
+            .send_deserialized(
+                endpoint,
+                Some(&request),
+                Some(&self.config.get_api_key().await),
+            )
+            .await?;
+
+        Ok(resp)
+    }
+
+    pub async fn get_payout(&self, payout_id: &str) -> Result<PayoutModel, Error> {
+        let endpoint = RestApiEndpoint::GetPayout;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: PayoutModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{payout_id}")))
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Cancels a payout that is not processed yet.
+    pub async fn cancel_payout(&self, payout_id: &str) -> Result<PayoutModel, Error> {
+        let endpoint = RestApiEndpoint::CancelPayout;
+        let api_key = self.config.get_api_key().await;
+
+        let resp: PayoutModel = self
+            .send_deserialized(endpoint, None::<&()>, Some(&format!("{api_key}/{payout_id}")))
+            .await?;
+
+        Ok(resp)
+    }
+
     pub async fn generate_checkout_widget(
         &self,
         request: CreateCashierSessionRequest,
         widget_type: CheckoutWidgetType,
-    ) -> Result<CheckoutWidgetModel, String> {
-        let _ = self.login().await.map_err(|e| e.to_string())?;
-        let session = self
-            .create_cashier_session(request)
-            .await
-            .map_err(|e| e.to_string())?;
+    ) -> Result<CheckoutWidgetModel, Error> {
+        let session = self.create_cashier_session(request).await?;
 
         let template = match widget_type {
             CheckoutWidgetType::Regular => CHECKOUT_WIDGET_TEMPLATE,
@@ -160,15 +576,13 @@ impl<C: RestApiConfig> RestApiClient<C> {
 
         let timeout = self.config.get_timeout().await;
         let response =
-            tokio::time::timeout(timeout, self.send_flurl(&endpoint, request, path_params)).await;
+            tokio::time::timeout(timeout, self.send_http(&endpoint, request, path_params)).await;
 
         let Ok(response) = response else {
-            let msg = format!(
-                "Failed {:?} {:?}: Timeout",
-                endpoint.get_http_method(),
-                endpoint
-            );
-            return Err(msg.into());
+            return Err(Error::Timeout {
+                method: endpoint.get_http_method(),
+                url: String::from(&endpoint),
+            });
         };
 
         response
@@ -179,6 +593,68 @@ impl<C: RestApiConfig> RestApiClient<C> {
         endpoint: RestApiEndpoint,
         request: Option<&R>,
         path_params: Option<&str>,
+    ) -> Result<T, Error> {
+        if !endpoint.requires_auth() {
+            return self
+                .send_with_retries(endpoint, request, path_params)
+                .await;
+        }
+
+        let access_token = self.get_access_token().await?;
+        let result = self
+            .send_with_retries(endpoint, request, path_params)
+            .await;
+
+        match result {
+            Err(error) if error.is_unauthorized() => {
+                self.renew_rejected_token(&access_token).await?;
+                self.send_with_retries(endpoint, request, path_params)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    async fn send_with_retries<R: Serialize + Debug, T: DeserializeOwned + Debug>(
+        &self,
+        endpoint: RestApiEndpoint,
+        request: Option<&R>,
+        path_params: Option<&str>,
+    ) -> Result<T, Error> {
+        let policy = self.config.get_retry_policy().await;
+        let mut attempt = 1;
+
+        loop {
+            let error = match self.send_attempt(endpoint, request, path_params).await {
+                Ok(resp) => return Ok(resp),
+                Err(error) => error,
+            };
+
+            if !policy.should_retry(&endpoint, &error, attempt) {
+                if attempt == 1 {
+                    return Err(error);
+                }
+
+                return Err(Error::RetriesExhausted {
+                    attempts: attempt,
+                    last_error: Box::new(error),
+                });
+            }
+
+            if std::env::var("DEBUG").is_ok() {
+                println!("attempt {} of {:?} failed: {}", attempt, endpoint, error);
+            }
+
+            tokio::time::sleep(policy.get_backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_attempt<R: Serialize + Debug, T: DeserializeOwned + Debug>(
+        &self,
+        endpoint: RestApiEndpoint,
+        request: Option<&R>,
+        path_params: Option<&str>,
     ) -> Result<T, Error> {
         if std::env::var("DEBUG").is_ok() {
             println!("execute send_deserialized: {:?} {:?}", endpoint, request);
@@ -187,17 +663,15 @@ impl<C: RestApiConfig> RestApiClient<C> {
         let timeout = self.config.get_timeout().await;
         let response = tokio::time::timeout(
             timeout,
-            self.send_flurl_deserialized(&endpoint, request, path_params),
+            self.send_http_deserialized(&endpoint, request, path_params),
         )
         .await;
 
         let Ok(response) = response else {
-            let msg = format!(
-                "Failed {:?} {:?}: Timeout",
-                endpoint.get_http_method(),
-                endpoint
-            );
-            return Err(msg.into());
+            return Err(Error::Timeout {
+                method: endpoint.get_http_method(),
+                url: String::from(&endpoint),
+            });
         };
 
         response
@@ -207,136 +681,135 @@ impl<C: RestApiConfig> RestApiClient<C> {
         &self,
         base_url: &str,
         endpoint: &RestApiEndpoint,
+        path_params: Option<&str>,
         query_string: Option<String>,
     ) -> String {
         let endpoint_str = String::from(endpoint);
+        let mut url = format!("{base_url}{endpoint_str}");
+
+        if let Some(path_params) = path_params {
+            url = format!("{url}/{path_params}");
+        }
 
-        if let Some(query_string) = query_string {
-            format!("{base_url}{endpoint_str}?{query_string}")
-        } else {
-            format!("{base_url}{endpoint_str}")
+        match query_string {
+            Some(query_string) if !query_string.is_empty() => format!("{url}?{query_string}"),
+            _ => url,
         }
     }
 
-    async fn send_flurl_deserialized<R: Serialize + Debug, T: DeserializeOwned + Debug>(
+    async fn send_http_deserialized<R: Serialize + Debug, T: DeserializeOwned + Debug>(
         &self,
         endpoint: &RestApiEndpoint,
         request: Option<&R>,
         path_params: Option<&str>,
     ) -> Result<T, Error> {
-        let response = self.send_flurl(endpoint, request, path_params).await?;
+        let response = self.send_http(endpoint, request, path_params).await?;
         let result: Result<Response<T>, _> = serde_json::from_str(&response);
 
-        let Ok(body) = result else {
-            let msg = format!(
-                "Failed to deserialize. Url: {:?} {:?}. Request: {:?}. Body: {}",
-                endpoint.get_http_method(),
-                String::from(endpoint),
-                request,
-                response
-            );
-            return Err(msg.into());
+        let url = format!("{:?} {}", endpoint.get_http_method(), String::from(endpoint));
+
+        let body = match result {
+            Ok(body) => body,
+            Err(err) => {
+                return Err(Error::Deserialize {
+                    url,
+                    body: response,
+                    message: err.to_string(),
+                })
+            }
         };
 
         if body.response.status != "OK" {
-            return Err(format!("Failed {:?} {:?}", endpoint, body.response).into());
+            return Err(Error::from(&body.response));
         }
 
-        Ok(body.result.unwrap())
+        let Some(result) = body.result else {
+            return Err(Error::Deserialize {
+                url,
+                body: response,
+                message: "Missing result".to_string(),
+            });
+        };
+
+        Ok(result)
     }
 
-    async fn send_flurl<R: Serialize + Debug>(
+    async fn send_http<R: Serialize + Debug>(
         &self,
         endpoint: &RestApiEndpoint,
         request: Option<&R>,
         path_params: Option<&str>,
     ) -> Result<String, Error> {
-        let mut request_json = None;
-
-        if let Some(request) = request {
-            let body = serde_json::to_string(request)?;
-            request_json = Some(body.clone());
-        }
-
-        let request_bytes: Option<Vec<u8>> = if let Some(request) = request {
-            Some(serde_json::to_string(request)?.into_bytes())
-        } else {
-            None
-        };
-        let (flurl, url) = self.build_flurl(endpoint, request, path_params).await?;
-        let http_method = endpoint.get_http_method();
+        let request = self.build_request(endpoint, request, path_params).await?;
+        let url = format!("{:?} {}", request.method, request.url);
 
-        let result = if http_method == Method::GET {
-            flurl.get().await
-        } else if http_method == Method::POST {
-            flurl.post(request_bytes).await
-        } else if http_method == Method::PUT {
-            flurl.put(request_bytes).await
-        } else if http_method == Method::PATCH {
-            flurl.patch(request_bytes).await
-        } else if http_method == Method::DELETE {
-            flurl.delete().await
-        } else {
-            panic!("not implemented");
+        let response = match self.transport.send(request).await {
+            Ok(response) => response,
+            Err(TransportError::Connect(message)) => return Err(Error::Connect { url, message }),
+            Err(TransportError::Receive(message)) => {
+                return Err(Error::Transport { url, message })
+            }
         };
 
-        let Ok(resp) = result else {
-            return Err(format!(
-                "FlUrl failed to receive_body: Url: {}. Request: {:?}. {:?}",
-                url,
-                request_json,
-                result.unwrap_err()
-            )
-            .into());
-        };
-
-        handle_flurl_text(resp, &request_json, &url, endpoint.get_http_method()).await
+        handle_response(response, url)
     }
 
-    pub async fn build_flurl<R: Serialize>(
+    pub async fn build_request<R: Serialize>(
         &self,
         endpoint: &RestApiEndpoint,
         request: Option<&R>,
         path_params: Option<&str>,
-    ) -> Result<(FlUrl, String), Error> {
+    ) -> Result<HttpRequest, Error> {
         let base_url = self.config.get_api_url().await;
         let http_method = endpoint.get_http_method();
 
-        let mut url = if http_method == Method::GET {
-            let query_string = serde_qs::to_string(&request).expect("must be valid model");
-            self.build_full_url(&base_url, endpoint, Some(query_string))
-        } else {
-            self.build_full_url(&base_url, endpoint, None)
+        let query_string = match request {
+            Some(request) if http_method == Method::GET => Some(
+                serde_qs::to_string(request).map_err(|err| Error::Serialize(err.to_string()))?,
+            ),
+            _ => None,
         };
+        let url = self.build_full_url(&base_url, endpoint, path_params, query_string);
 
-        if let Some(path_params) = path_params {
-            url = format!("{url}/{path_params}");
-        }
-
-        let flurl = self.add_headers(FlUrl::new(&url)).await;
+        let body = match request {
+            Some(request) if http_method != Method::GET => {
+                Some(serde_json::to_string(request)?.into_bytes())
+            }
+            _ => None,
+        };
 
-        Ok((flurl, url))
+        Ok(HttpRequest {
+            method: http_method,
+            url,
+            headers: self.build_headers().await,
+            body,
+        })
     }
 
-    async fn add_headers(&self, flurl: FlUrl) -> FlUrl {
+    async fn build_headers(&self) -> Vec<(String, String)> {
         let json_content_str = "application/json";
 
-        let mut flurl = flurl
-            .with_header("Content-Type", json_content_str)
-            .with_header("Accept", json_content_str)
-            .with_header(
-                "Host",
-                self.config.get_api_url().await.replace("https://", ""),
-            );
+        let mut headers = vec![
+            ("Content-Type".to_string(), json_content_str.to_string()),
+            ("Accept".to_string(), json_content_str.to_string()),
+            (
+                "Host".to_string(),
+                self.config
+                    .get_api_url()
+                    .await
+                    .replace("https://", "")
+                    .replace("http://", ""),
+            ),
+        ];
 
-        if let Some(result) = self.login_result.lock().unwrap().as_ref() {
-            flurl = flurl.with_header(
-                "Authorization",
-                format!("Bearer {}", result.access_token.token),
-            );
+        if let Some(credentials) = self.credentials.get() {
+            headers.push((
+                "Authorization".to_string(),
+                format!("Bearer {}", credentials.access_token()),
+            ));
         }
 
-        flurl
+        headers
     }
 
     pub fn build_query_string(&self, params: Vec<(&str, &str)>) -> String {
@@ -353,55 +826,540 @@ impl<C: RestApiConfig> RestApiClient<C> {
     }
 }
 
-async fn handle_flurl_text(
-    response: FlUrlResponse,
-    request_json: &Option<String>,
-    request_url: &str,
-    request_method: Method,
-) -> Result<String, Error> {
-    let status_code = StatusCode::from_u16(response.get_status_code()).unwrap();
-    let result = response.receive_body().await;
-
-    let Ok(body_bytes) = result else {
-        return Err(format!("FlUrl failed to receive_body: {:?}", result.unwrap_err()).into());
+fn handle_response(response: HttpResponse, url: String) -> Result<String, Error> {
+    let Ok(status_code) = StatusCode::from_u16(response.status) else {
+        return Err(Error::InvalidStatus {
+            url,
+            status: response.status,
+        });
     };
-
-    let body_str = String::from_utf8(body_bytes).unwrap();
+    let body_str = String::from_utf8_lossy(&response.body).into_owned();
 
     match status_code {
         StatusCode::OK | StatusCode::CREATED | StatusCode::NO_CONTENT => Ok(body_str),
-        StatusCode::INTERNAL_SERVER_ERROR => {
-            bail!(format!(
-                "Internal Server Error. Url: {request_method:?} {request_url}"
-            ));
+        code => Err(Error::from_status(url, code, body_str)),
+    }
+}
+
+struct ListTransactionsState {
+    /// Request of the next page.
+    request: ListTransactionsRequest,
+    /// Not yet returned transactions of the last received page.
+    transactions: VecDeque<TransactionModel>,
+    has_more: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RestApiClient, RestApiConfig};
+    use crate::rest::errors::Error;
+    use crate::rest::retry::RetryPolicy;
+    use crate::rest::transport::{HttpRequest, HttpResponse, InMemoryTransport};
+    use crate::money::Amount;
+    use crate::rest::{
+        CaptureRequest, ChargeTokenRequest, CreateCashierSessionRequest, ListTransactionsRequest,
+    };
+    use crate::webhook::test_support::webhook_json;
+    use crate::webhook::{ChargeAttributesStatus, WebhookPayload};
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct TestConfig;
+
+    #[async_trait::async_trait]
+    impl RestApiConfig for TestConfig {
+        async fn get_api_url(&self) -> String {
+            "https://api.test".to_string()
         }
-        StatusCode::SERVICE_UNAVAILABLE => {
-            bail!(format!(
-                "Service Unavailable. Url: {request_method:?} {request_url}"
-            ));
+
+        async fn get_api_key(&self) -> String {
+            "api-key".to_string()
         }
-        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-            bail!(format!(
-                "Unauthorized or forbidden. Url: {request_method:?} {request_url}"
-            ));
+
+        async fn get_timeout(&self) -> Duration {
+            Duration::from_secs(1)
         }
-        StatusCode::BAD_REQUEST => {
-            let error = body_str;
-            bail!(format!(
-                "Received bad request status. Url: {request_method:?} {request_url}. Request: {request_json:?}. Response: {error:?}"
-            ));
+
+        async fn get_user_name(&self) -> String {
+            "user".to_string()
         }
-        code => {
-            let error = body_str;
-            bail!(format!("Received response code: {code:?}. Url: {request_method:?} {request_url}. Request: {request_json:?} Response: {error:?}"));
+
+        async fn get_password(&self) -> String {
+            "password".to_string()
+        }
+
+        async fn get_cashier_key(&self) -> String {
+            "cashier-key".to_string()
+        }
+
+        async fn get_retry_policy(&self) -> RetryPolicy {
+            RetryPolicy {
+                base_backoff: Duration::from_millis(1),
+                ..Default::default()
+            }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    fn ok(result: serde_json::Value) -> HttpResponse {
+        let body = serde_json::json!({
+            "response": { "status": "OK", "code": 200, "message": "" },
+            "result": result,
+        });
+
+        HttpResponse {
+            status: 200,
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn login_result(token: &str, expires_in: i64) -> serde_json::Value {
+        serde_json::json!({
+            "refresh_token": format!("refresh-{token}"),
+            "access_token": { "token": token, "expires_in": expires_in },
+        })
+    }
+
+    fn session_request() -> CreateCashierSessionRequest {
+        serde_json::from_value(serde_json::json!({
+            "cashier_key": null,
+            "order_id": "order-1",
+            "currency": "USD",
+            "country": "US",
+        }))
+        .unwrap()
+    }
+
+    fn count(requests: &[HttpRequest], path: &str) -> usize {
+        requests.iter().filter(|r| r.url.contains(path)).count()
+    }
+
     #[test]
     fn works() {}
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_login() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            Ok(ok(serde_json::json!({ "cashier_token": "cashier-token" })))
+        })
+        .with_delay(Duration::from_millis(20));
+        let client = Arc::new(RestApiClient::with_transport(TestConfig, transport));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.create_cashier_session(session_request()).await })
+            })
+            .collect();
+
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+
+        let requests = client.transport.get_requests();
+        assert_eq!(count(&requests, "/auth/login"), 1);
+        assert_eq!(count(&requests, "/cashier/session/create"), 10);
+    }
+
+    #[tokio::test]
+    async fn keeps_short_lived_token_until_half_of_lifetime() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                // shorter than the refresh margin
+                return Ok(ok(login_result("token", 30)));
+            }
+
+            Ok(ok(serde_json::json!({ "cashier_token": "cashier-token" })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        for _ in 0..3 {
+            client.create_cashier_session(session_request()).await.unwrap();
+        }
+
+        let requests = client.transport.get_requests();
+        assert_eq!(count(&requests, "/auth/login"), 1);
+        assert_eq!(count(&requests, "/auth/refresh_token"), 0);
+    }
+
+    #[tokio::test]
+    async fn refreshes_expiring_token_and_retries_on_unauthorized() {
+        let refreshes = AtomicUsize::new(0);
+        let transport = InMemoryTransport::new(move |request| {
+            if request.url.ends_with("/auth/login") {
+                // already expired
+                return Ok(ok(login_result("first", 0)));
+            }
+
+            if request.url.ends_with("/auth/refresh_token") {
+                let token = match refreshes.fetch_add(1, Ordering::SeqCst) {
+                    0 => "second",
+                    _ => "third",
+                };
+                return Ok(ok(login_result(token, 3600)));
+            }
+
+            if request.get_header("Authorization") == Some("Bearer second") {
+                return Ok(HttpResponse {
+                    status: 401,
+                    body: vec![],
+                });
+            }
+
+            assert_eq!(request.get_header("Authorization"), Some("Bearer third"));
+            Ok(ok(serde_json::json!({ "cashier_token": "cashier-token" })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        client.login().await.unwrap();
+        let resp = client.create_cashier_session(session_request()).await.unwrap();
+
+        assert_eq!(resp.cashier_token, "cashier-token");
+        let requests = client.transport.get_requests();
+        assert_eq!(count(&requests, "/auth/login"), 1);
+        assert_eq!(count(&requests, "/auth/refresh_token"), 2);
+        assert_eq!(count(&requests, "/cashier/session/create"), 2);
+    }
+
+    #[tokio::test]
+    async fn gets_transaction_by_charge_id() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            Ok(ok(serde_json::json!({
+                "order_id": "order-1",
+                "psp_name": "psp",
+                "charge": {
+                    "type": "charge",
+                    "id": "charge-1",
+                    "attributes": { "status": "approved", "amount": 10.5, "created_at": 1 },
+                },
+            })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let resp = client.get_transaction_by_charge_id("charge-1").await.unwrap();
+
+        assert_eq!(resp.charge.id.as_deref(), Some("charge-1"));
+        let request = client.transport.get_requests().pop().unwrap();
+        assert_eq!(request.method, http::Method::GET);
+        assert_eq!(
+            request.url,
+            "https://api.test/v2/transactions/charge/api-key/charge-1"
+        );
+        assert!(request.body.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_refund_above_remaining_amount() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            let deposit = serde_json::json!({
+                "order_id": "order-1",
+                "charge": {
+                    "type": "charge",
+                    "id": "charge-1",
+                    "operation_type": "deposit",
+                    "attributes": { "status": "approved", "amount": 10, "currency": "USD", "created_at": 1 },
+                },
+            });
+            let refund = serde_json::json!({
+                "order_id": "order-1",
+                "charge": {
+                    "type": "charge",
+                    "id": "charge-2",
+                    "operation_type": "refund",
+                    "attributes": { "status": "approved", "amount": 4, "currency": "USD", "created_at": 2 },
+                },
+            });
+
+            if request.url.contains("/transactions/order/") {
+                Ok(ok(serde_json::json!([deposit, refund])))
+            } else if request.url.contains("/transactions/charge/") {
+                Ok(ok(deposit))
+            } else {
+                Ok(ok(refund))
+            }
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let error = client
+            .refund("charge-1", Some("6.01".parse().unwrap()), "duplicate")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Validation(_)));
+        assert_eq!(
+            count(&client.transport.get_requests(), "/transactions/refund"),
+            0
+        );
+
+        client.refund("charge-1", None, "duplicate").await.unwrap();
+        let request = client.transport.get_requests().pop().unwrap();
+        assert_eq!(
+            request.url,
+            "https://api.test/v2/transactions/refund/api-key"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(body["amount"], 6.0);
+    }
+
+    #[tokio::test]
+    async fn captures_authorized_webhook_charge() {
+        let charge = serde_json::json!({
+            "type": "charge",
+            "id": "charge-1",
+            "attributes": { "status": "authorized", "amount": 25.5, "currency": "EUR", "created_at": 1 },
+        });
+        let payload: WebhookPayload =
+            serde_json::from_value(webhook_json("authorized", charge.clone())).unwrap();
+        let transport = InMemoryTransport::new(move |request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            Ok(ok(serde_json::json!({ "order_id": "order-1", "charge": charge })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let capture = CaptureRequest::from_authorized_webhook(&payload).unwrap();
+        client.capture(&capture.charge_id, capture.amount).await.unwrap();
+
+        let request = client.transport.get_requests().pop().unwrap();
+        assert_eq!(request.url, "https://api.test/v2/transactions/capture/api-key");
+        let body: serde_json::Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({ "charge_id": "charge-1", "amount": 25.5 }));
+    }
+
+    #[tokio::test]
+    async fn streams_all_transaction_pages() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            let page: u32 = if request.url.contains("page=2") { 2 } else { 1 };
+            let transactions: Vec<_> = (0..2)
+                .map(|i| {
+                    serde_json::json!({
+                        "order_id": format!("order-{page}-{i}"),
+                        "charge": {
+                            "type": "charge",
+                            "attributes": { "status": "approved", "created_at": 1 },
+                        },
+                    })
+                })
+                .collect();
+
+            Ok(ok(serde_json::json!({
+                "transactions": transactions,
+                "page": page,
+                "total_pages": 2,
+            })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+        let request = ListTransactionsRequest {
+            from: Some(1700000000),
+            status: Some(ChargeAttributesStatus::Approved),
+            currency: Some("USD".to_string()),
+            ..Default::default()
+        };
+
+        let order_ids: Vec<_> = client
+            .list_transactions(request)
+            .map(|transaction| transaction.unwrap().order_id)
+            .collect()
+            .await;
+
+        assert_eq!(order_ids, ["order-1-0", "order-1-1", "order-2-0", "order-2-1"]);
+        let requests = client.transport.get_requests();
+        assert_eq!(count(&requests, "/v2/transactions/api-key?"), 2);
+        assert_eq!(
+            requests.last().unwrap().url,
+            "https://api.test/v2/transactions/api-key?from=1700000000&status=approved&currency=USD&page=2"
+        );
+    }
+
+    #[tokio::test]
+    async fn charges_stored_card_token() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            Ok(ok(serde_json::json!({
+                "order_id": "order-2",
+                "charge": {
+                    "type": "charge",
+                    "is_recurring": true,
+                    "attributes": { "status": "declined", "decline_reason": "Insufficient funds", "created_at": 1 },
+                },
+            })))
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+        let request = ChargeTokenRequest::builder("order-2", "card-token", Amount::from(49), "USD")
+            .is_recurring(true)
+            .build()
+            .unwrap();
+
+        let resp = client.charge_token(request).await.unwrap();
+
+        assert_eq!(resp.charge.attributes.status, ChargeAttributesStatus::Declined);
+        let request = client.transport.get_requests().pop().unwrap();
+        assert_eq!(request.url, "https://api.test/v2/transactions/token/api-key");
+        let body: serde_json::Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(body["cashier_key"], "cashier-key");
+        assert_eq!(body["credit_card_token"], "card-token");
+        assert_eq!(body["amount"], 49.0);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_reports_attempts() {
+        let transport = InMemoryTransport::new(|_| {
+            Ok(HttpResponse {
+                status: 503,
+                body: vec![],
+            })
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let error = client.login().await.unwrap_err();
+
+        assert_eq!(error.attempts(), 3);
+        assert!(matches!(error.last_error(), Error::ServerError { status: 503, .. }));
+        assert_eq!(client.transport.get_requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_non_idempotent_requests_after_server_error() {
+        let transport = InMemoryTransport::new(|request| {
+            if request.url.ends_with("/auth/login") {
+                return Ok(ok(login_result("token", 3600)));
+            }
+
+            Ok(HttpResponse {
+                status: 500,
+                body: vec![],
+            })
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let error = client
+            .create_cashier_session(session_request())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 1);
+        assert_eq!(
+            count(&client.transport.get_requests(), "/cashier/session/create"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_status_code() {
+        let transport = InMemoryTransport::new(|_| {
+            Ok(HttpResponse {
+                status: 1000,
+                body: vec![],
+            })
+        });
+        let client = RestApiClient::with_transport(TestConfig, transport);
+
+        let error = client.login().await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidStatus { status: 1000, .. }));
+    }
+
+    struct LocalConfig(String);
+
+    #[async_trait::async_trait]
+    impl RestApiConfig for LocalConfig {
+        async fn get_api_url(&self) -> String {
+            self.0.clone()
+        }
+
+        async fn get_api_key(&self) -> String {
+            TestConfig.get_api_key().await
+        }
+
+        async fn get_timeout(&self) -> Duration {
+            TestConfig.get_timeout().await
+        }
+
+        async fn get_user_name(&self) -> String {
+            TestConfig.get_user_name().await
+        }
+
+        async fn get_password(&self) -> String {
+            TestConfig.get_password().await
+        }
+
+        async fn get_cashier_key(&self) -> String {
+            TestConfig.get_cashier_key().await
+        }
+
+        async fn get_retry_policy(&self) -> RetryPolicy {
+            TestConfig.get_retry_policy().await
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_resend_request_after_connection_drops() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let server_dropped = dropped.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+
+                // the client sends the whole request before reading the response
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await.unwrap() {
+                        0 => break,
+                        read => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+
+                if !String::from_utf8_lossy(&request).contains("/auth/login") {
+                    server_dropped.fetch_add(1, Ordering::SeqCst);
+                    continue;
+                }
+
+                let body = ok(login_result("token", 3600)).body;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+            }
+        });
+
+        let client = RestApiClient::new(LocalConfig(url));
+        let error = client
+            .create_cashier_session(session_request())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::Transport { .. }), "{error:?}");
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -0,0 +1,390 @@
+use crate::money::Amount;
+use crate::rest::validation::FieldError;
+use crate::rest::{
+    ApplePayModel, CaptureRequest, ChargeTokenRequest, CheckoutTheme,
+    CreateCashierSessionRequest, CreatePaymentLinkRequest, CreatePayoutRequest,
+    WireTransferDetails,
+};
+use crate::webhook::{ChargeAttributesStatus, WebhookPayload, WebhookType};
+
+impl CreateCashierSessionRequest {
+    pub fn builder(
+        order_id: impl Into<String>,
+        currency: impl Into<String>,
+        country: impl Into<String>,
+    ) -> CreateCashierSessionRequestBuilder {
+        CreateCashierSessionRequestBuilder::new(order_id, currency, country)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateCashierSessionRequestBuilder {
+    request: CreateCashierSessionRequest,
+}
+
+impl CreateCashierSessionRequestBuilder {
+    pub fn new(
+        order_id: impl Into<String>,
+        currency: impl Into<String>,
+        country: impl Into<String>,
+    ) -> Self {
+        Self {
+            request: CreateCashierSessionRequest {
+                cashier_key: None,
+                order_id: order_id.into(),
+                currency: currency.into(),
+                country: country.into(),
+                amount: None,
+                theme: None,
+                first_name: None,
+                last_name: None,
+                phone: None,
+                email: None,
+                zip_code: None,
+                payload: None,
+                currency_lock: None,
+                amount_lock: None,
+                platform_id: None,
+                tracking_id: None,
+                affiliate_id: None,
+                city: None,
+                address: None,
+                state: None,
+                hide_languages_dropdown: None,
+                language: None,
+                apple_pay: None,
+                button_text: None,
+                deposit_button_text: None,
+                pay_mode: None,
+            },
+        }
+    }
+
+    pub fn cashier_key(mut self, cashier_key: impl Into<String>) -> Self {
+        self.request.cashier_key = Some(cashier_key.into());
+        self
+    }
+
+    pub fn amount(mut self, amount: Amount) -> Self {
+        self.request.amount = Some(amount);
+        self
+    }
+
+    pub fn theme(mut self, theme: CheckoutTheme) -> Self {
+        self.request.theme = Some(theme);
+        self
+    }
+
+    pub fn first_name(mut self, first_name: impl Into<String>) -> Self {
+        self.request.first_name = Some(first_name.into());
+        self
+    }
+
+    pub fn last_name(mut self, last_name: impl Into<String>) -> Self {
+        self.request.last_name = Some(last_name.into());
+        self
+    }
+
+    pub fn phone(mut self, phone: impl Into<String>) -> Self {
+        self.request.phone = Some(phone.into());
+        self
+    }
+
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.request.email = Some(email.into());
+        self
+    }
+
+    pub fn zip_code(mut self, zip_code: impl Into<String>) -> Self {
+        self.request.zip_code = Some(zip_code.into());
+        self
+    }
+
+    pub fn payload(mut self, payload: impl Into<String>) -> Self {
+        self.request.payload = Some(payload.into());
+        self
+    }
+
+    pub fn currency_lock(mut self, currency_lock: bool) -> Self {
+        self.request.currency_lock = Some(currency_lock);
+        self
+    }
+
+    pub fn amount_lock(mut self, amount_lock: bool) -> Self {
+        self.request.amount_lock = Some(amount_lock);
+        self
+    }
+
+    pub fn platform_id(mut self, platform_id: impl Into<String>) -> Self {
+        self.request.platform_id = Some(platform_id.into());
+        self
+    }
+
+    pub fn tracking_id(mut self, tracking_id: impl Into<String>) -> Self {
+        self.request.tracking_id = Some(tracking_id.into());
+        self
+    }
+
+    pub fn affiliate_id(mut self, affiliate_id: impl Into<String>) -> Self {
+        self.request.affiliate_id = Some(affiliate_id.into());
+        self
+    }
+
+    pub fn city(mut self, city: impl Into<String>) -> Self {
+        self.request.city = Some(city.into());
+        self
+    }
+
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.request.address = Some(address.into());
+        self
+    }
+
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.request.state = Some(state.into());
+        self
+    }
+
+    pub fn hide_languages_dropdown(mut self, hide_languages_dropdown: bool) -> Self {
+        self.request.hide_languages_dropdown = Some(hide_languages_dropdown);
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.request.language = Some(language.into());
+        self
+    }
+
+    pub fn apple_pay(mut self, apple_pay: ApplePayModel) -> Self {
+        self.request.apple_pay = Some(apple_pay);
+        self
+    }
+
+    pub fn button_text(mut self, button_text: impl Into<String>) -> Self {
+        self.request.button_text = Some(button_text.into());
+        self
+    }
+
+    pub fn deposit_button_text(mut self, deposit_button_text: impl Into<String>) -> Self {
+        self.request.deposit_button_text = Some(deposit_button_text.into());
+        self
+    }
+
+    pub fn pay_mode(mut self, pay_mode: bool) -> Self {
+        self.request.pay_mode = Some(pay_mode);
+        self
+    }
+
+    /// Validates the request before any HTTP call is made.
+    pub fn build(self) -> Result<CreateCashierSessionRequest, Vec<FieldError>> {
+        self.request.validate()?;
+
+        Ok(self.request)
+    }
+}
+
+impl CreatePaymentLinkRequest {
+    pub fn new(session: CreateCashierSessionRequest) -> Self {
+        Self {
+            session,
+            expires_at: None,
+            single_use: None,
+        }
+    }
+
+    /// Unix time in seconds after which the link can't be paid.
+    pub fn expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn single_use(mut self, single_use: bool) -> Self {
+        self.single_use = Some(single_use);
+        self
+    }
+}
+
+impl ChargeTokenRequest {
+    pub fn builder(
+        order_id: impl Into<String>,
+        credit_card_token: impl Into<String>,
+        amount: Amount,
+        currency: impl Into<String>,
+    ) -> ChargeTokenRequestBuilder {
+        ChargeTokenRequestBuilder::new(order_id, credit_card_token, amount, currency)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChargeTokenRequestBuilder {
+    request: ChargeTokenRequest,
+}
+
+impl ChargeTokenRequestBuilder {
+    pub fn new(
+        order_id: impl Into<String>,
+        credit_card_token: impl Into<String>,
+        amount: Amount,
+        currency: impl Into<String>,
+    ) -> Self {
+        Self {
+            request: ChargeTokenRequest {
+                cashier_key: None,
+                order_id: order_id.into(),
+                credit_card_token: credit_card_token.into(),
+                amount,
+                currency: currency.into(),
+                is_recurring: None,
+                description: None,
+                payload: None,
+                platform_id: None,
+            },
+        }
+    }
+
+    pub fn cashier_key(mut self, cashier_key: impl Into<String>) -> Self {
+        self.request.cashier_key = Some(cashier_key.into());
+        self
+    }
+
+    pub fn is_recurring(mut self, is_recurring: bool) -> Self {
+        self.request.is_recurring = Some(is_recurring);
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.request.description = Some(description.into());
+        self
+    }
+
+    pub fn payload(mut self, payload: impl Into<String>) -> Self {
+        self.request.payload = Some(payload.into());
+        self
+    }
+
+    pub fn platform_id(mut self, platform_id: impl Into<String>) -> Self {
+        self.request.platform_id = Some(platform_id.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ChargeTokenRequest, Vec<FieldError>> {
+        self.request.validate()?;
+
+        Ok(self.request)
+    }
+}
+
+impl CreatePayoutRequest {
+    pub fn builder(
+        order_id: impl Into<String>,
+        currency: impl Into<String>,
+        country: impl Into<String>,
+        amount: Amount,
+    ) -> CreatePayoutRequestBuilder {
+        CreatePayoutRequestBuilder::new(order_id, currency, country, amount)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatePayoutRequestBuilder {
+    request: CreatePayoutRequest,
+}
+
+impl CreatePayoutRequestBuilder {
+    pub fn new(
+        order_id: impl Into<String>,
+        currency: impl Into<String>,
+        country: impl Into<String>,
+        amount: Amount,
+    ) -> Self {
+        Self {
+            request: CreatePayoutRequest {
+                cashier_key: None,
+                order_id: order_id.into(),
+                currency: currency.into(),
+                country: country.into(),
+                amount,
+                credit_card_token: None,
+                wire_transfer: None,
+                first_name: None,
+                last_name: None,
+                email: None,
+                phone: None,
+                payload: None,
+                platform_id: None,
+            },
+        }
+    }
+
+    pub fn cashier_key(mut self, cashier_key: impl Into<String>) -> Self {
+        self.request.cashier_key = Some(cashier_key.into());
+        self
+    }
+
+    pub fn credit_card_token(mut self, credit_card_token: impl Into<String>) -> Self {
+        self.request.credit_card_token = Some(credit_card_token.into());
+        self
+    }
+
+    pub fn wire_transfer(mut self, wire_transfer: WireTransferDetails) -> Self {
+        self.request.wire_transfer = Some(wire_transfer);
+        self
+    }
+
+    pub fn first_name(mut self, first_name: impl Into<String>) -> Self {
+        self.request.first_name = Some(first_name.into());
+        self
+    }
+
+    pub fn last_name(mut self, last_name: impl Into<String>) -> Self {
+        self.request.last_name = Some(last_name.into());
+        self
+    }
+
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.request.email = Some(email.into());
+        self
+    }
+
+    pub fn phone(mut self, phone: impl Into<String>) -> Self {
+        self.request.phone = Some(phone.into());
+        self
+    }
+
+    pub fn payload(mut self, payload: impl Into<String>) -> Self {
+        self.request.payload = Some(payload.into());
+        self
+    }
+
+    pub fn platform_id(mut self, platform_id: impl Into<String>) -> Self {
+        self.request.platform_id = Some(platform_id.into());
+        self
+    }
+
+    pub fn build(self) -> Result<CreatePayoutRequest, Vec<FieldError>> {
+        self.request.validate()?;
+
+        Ok(self.request)
+    }
+}
+
+impl CaptureRequest {
+    /// Capture of the full authorized amount of an `authorized` webhook charge. `None` for other
+    /// webhook types and charges without an id or not in the authorized status.
+    pub fn from_authorized_webhook(payload: &WebhookPayload) -> Option<CaptureRequest> {
+        if payload.webhook.webhook_type != WebhookType::Authorized {
+            return None;
+        }
+
+        let charge = payload.data.charge.as_ref()?;
+
+        if charge.attributes.status != ChargeAttributesStatus::Authorized {
+            return None;
+        }
+
+        Some(CaptureRequest {
+            charge_id: charge.id.clone()?,
+            amount: charge.attributes.amount,
+        })
+    }
+}
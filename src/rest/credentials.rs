@@ -0,0 +1,65 @@
+use crate::rest::LoginModel;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub login: LoginModel,
+    pub issued_at: Instant,
+}
+
+impl Credentials {
+    pub fn new(login: LoginModel) -> Self {
+        Self {
+            login,
+            issued_at: Instant::now(),
+        }
+    }
+
+    pub fn access_token(&self) -> &str {
+        &self.login.access_token.token
+    }
+
+    pub fn expires_at(&self) -> Instant {
+        let expires_in = self.login.access_token.expires_in.max(0) as u64;
+        self.issued_at + Duration::from_secs(expires_in)
+    }
+
+    /// Whether the token expires within the margin. The margin is capped at half of the token
+    /// lifetime, so short-lived tokens are not renewed on every request.
+    pub fn is_expiring(&self, margin: Duration) -> bool {
+        let expires_at = self.expires_at();
+        let margin = margin.min((expires_at - self.issued_at) / 2);
+
+        Instant::now() + margin >= expires_at
+    }
+}
+
+/// Login credentials shared between concurrent requests.
+///
+/// Reads never wait on a login in progress. Logins and refreshes are serialized with
+/// [`CredentialCache::lock_renewal`], so callers queued behind an in-flight renewal can
+/// re-check the cache and reuse its result instead of authenticating again.
+#[derive(Debug, Default)]
+pub struct CredentialCache {
+    current: RwLock<Option<Credentials>>,
+    renewal: Mutex<()>,
+}
+
+impl CredentialCache {
+    pub fn get(&self) -> Option<Credentials> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn set(&self, login: LoginModel) -> Credentials {
+        let credentials = Credentials::new(login);
+        self.current.write().unwrap().replace(credentials.clone());
+
+        credentials
+    }
+
+    pub async fn lock_renewal(&self) -> MutexGuard<'_, ()> {
+        self.renewal.lock().await
+    }
+}
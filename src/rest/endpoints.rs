@@ -1,9 +1,25 @@
 use http::Method;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(strum::EnumIter, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RestApiEndpoint {
     AuthLogin,
+    AuthRefreshToken,
     CreateCashierSession,
+    GetTransactionsByOrderId,
+    GetTransactionByChargeId,
+    Refund,
+    CreatePayout,
+    GetPayout,
+    CancelPayout,
+    Capture,
+    Void,
+    ListTransactions,
+    ChargeToken,
+    GetCashierSession,
+    CancelCashierSession,
+    CreatePaymentLink,
+    GetPaymentLink,
+    ExpirePaymentLink,
 }
 
 impl From<&RestApiEndpoint> for String {
@@ -12,9 +28,31 @@ impl From<&RestApiEndpoint> for String {
 
         match item {
             RestApiEndpoint::AuthLogin => format!("/{api_version}/auth/login"),
+            RestApiEndpoint::AuthRefreshToken => format!("/{api_version}/auth/refresh_token"),
             RestApiEndpoint::CreateCashierSession => {
                 format!("/{api_version}/cashier/session/create")
             }
+            RestApiEndpoint::GetTransactionsByOrderId => {
+                format!("/{api_version}/transactions/order")
+            }
+            RestApiEndpoint::GetTransactionByChargeId => {
+                format!("/{api_version}/transactions/charge")
+            }
+            RestApiEndpoint::Refund => format!("/{api_version}/transactions/refund"),
+            RestApiEndpoint::CreatePayout => format!("/{api_version}/payouts/create"),
+            RestApiEndpoint::GetPayout => format!("/{api_version}/payouts"),
+            RestApiEndpoint::CancelPayout => format!("/{api_version}/payouts/cancel"),
+            RestApiEndpoint::Capture => format!("/{api_version}/transactions/capture"),
+            RestApiEndpoint::Void => format!("/{api_version}/transactions/void"),
+            RestApiEndpoint::ListTransactions => format!("/{api_version}/transactions"),
+            RestApiEndpoint::ChargeToken => format!("/{api_version}/transactions/token"),
+            RestApiEndpoint::GetCashierSession => format!("/{api_version}/cashier/session"),
+            RestApiEndpoint::CancelCashierSession => {
+                format!("/{api_version}/cashier/session/cancel")
+            }
+            RestApiEndpoint::CreatePaymentLink => format!("/{api_version}/payment-links/create"),
+            RestApiEndpoint::GetPaymentLink => format!("/{api_version}/payment-links"),
+            RestApiEndpoint::ExpirePaymentLink => format!("/{api_version}/payment-links/expire"),
         }
     }
 }
@@ -23,7 +61,71 @@ impl RestApiEndpoint {
     pub fn get_http_method(&self) -> Method {
         match &self {
             RestApiEndpoint::AuthLogin => Method::POST,
+            RestApiEndpoint::AuthRefreshToken => Method::POST,
             RestApiEndpoint::CreateCashierSession => Method::POST,
+            RestApiEndpoint::GetTransactionsByOrderId => Method::GET,
+            RestApiEndpoint::GetTransactionByChargeId => Method::GET,
+            RestApiEndpoint::Refund => Method::POST,
+            RestApiEndpoint::CreatePayout => Method::POST,
+            RestApiEndpoint::GetPayout => Method::GET,
+            RestApiEndpoint::CancelPayout => Method::POST,
+            RestApiEndpoint::Capture => Method::POST,
+            RestApiEndpoint::Void => Method::POST,
+            RestApiEndpoint::ListTransactions => Method::GET,
+            RestApiEndpoint::ChargeToken => Method::POST,
+            RestApiEndpoint::GetCashierSession => Method::GET,
+            RestApiEndpoint::CancelCashierSession => Method::POST,
+            RestApiEndpoint::CreatePaymentLink => Method::POST,
+            RestApiEndpoint::GetPaymentLink => Method::GET,
+            RestApiEndpoint::ExpirePaymentLink => Method::POST,
+        }
+    }
+
+    pub fn requires_auth(&self) -> bool {
+        match &self {
+            RestApiEndpoint::AuthLogin => false,
+            RestApiEndpoint::AuthRefreshToken => false,
+            RestApiEndpoint::CreateCashierSession => true,
+            RestApiEndpoint::GetTransactionsByOrderId => true,
+            RestApiEndpoint::GetTransactionByChargeId => true,
+            RestApiEndpoint::Refund => true,
+            RestApiEndpoint::CreatePayout => true,
+            RestApiEndpoint::GetPayout => true,
+            RestApiEndpoint::CancelPayout => true,
+            RestApiEndpoint::Capture => true,
+            RestApiEndpoint::Void => true,
+            RestApiEndpoint::ListTransactions => true,
+            RestApiEndpoint::ChargeToken => true,
+            RestApiEndpoint::GetCashierSession => true,
+            RestApiEndpoint::CancelCashierSession => true,
+            RestApiEndpoint::CreatePaymentLink => true,
+            RestApiEndpoint::GetPaymentLink => true,
+            RestApiEndpoint::ExpirePaymentLink => true,
+        }
+    }
+
+    /// Whether sending the same request twice has the same effect as sending it once.
+    /// Default of [`crate::rest::retry::RetryPolicy::idempotent_endpoints`].
+    pub fn is_idempotent(&self) -> bool {
+        match &self {
+            RestApiEndpoint::AuthLogin => true,
+            RestApiEndpoint::AuthRefreshToken => false,
+            RestApiEndpoint::CreateCashierSession => false,
+            RestApiEndpoint::GetTransactionsByOrderId => true,
+            RestApiEndpoint::GetTransactionByChargeId => true,
+            RestApiEndpoint::Refund => false,
+            RestApiEndpoint::CreatePayout => false,
+            RestApiEndpoint::GetPayout => true,
+            RestApiEndpoint::CancelPayout => true,
+            RestApiEndpoint::Capture => false,
+            RestApiEndpoint::Void => true,
+            RestApiEndpoint::ListTransactions => true,
+            RestApiEndpoint::ChargeToken => false,
+            RestApiEndpoint::GetCashierSession => true,
+            RestApiEndpoint::CancelCashierSession => true,
+            RestApiEndpoint::CreatePaymentLink => false,
+            RestApiEndpoint::GetPaymentLink => true,
+            RestApiEndpoint::ExpirePaymentLink => true,
         }
     }
 }
@@ -1,17 +1,207 @@
-use error_chain::error_chain;
+use crate::rest::validation::FieldError;
+use crate::rest::ResponseModel;
+use http::{Method, StatusCode};
+use std::fmt;
 
-error_chain! {
-    errors {
-       RestError(response: String)
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// No response within `RestApiConfig::get_timeout`.
+    Timeout { method: Method, url: String },
+    /// The request never reached the server, so it is safe to send again.
+    Connect { url: String, message: String },
+    /// The request may have reached the server, so it is only sent again to idempotent
+    /// endpoints.
+    Transport { url: String, message: String },
+    /// 401 or 403 status code.
+    Unauthorized { url: String },
+    /// 400 status code.
+    BadRequest { url: String, body: String },
+    /// 5xx status code.
+    ServerError {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    /// Any other non-success status code.
+    UnexpectedStatus {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    /// The transport returned a status code outside of 100-999.
+    InvalidStatus { url: String, status: u16 },
+    /// BridgerPay responded with a `response.status` other than `OK`.
+    ApiError {
+        status: String,
+        code: i32,
+        message: String,
+    },
+    /// The response body doesn't match the expected model.
+    Deserialize {
+        url: String,
+        body: String,
+        message: String,
+    },
+    /// The request model can't be serialized.
+    Serialize(String),
+    /// The request model is rejected before sending.
+    Validation(Vec<FieldError>),
+    NotLoggedIn,
+    /// The last error of a request that was sent more than once.
+    RetriesExhausted {
+        attempts: u32,
+        last_error: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn from_status(url: String, status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized { url },
+            StatusCode::BAD_REQUEST => Error::BadRequest { url, body },
+            status if status.is_server_error() => Error::ServerError {
+                url,
+                status: status.as_u16(),
+                body,
+            },
+            status => Error::UnexpectedStatus {
+                url,
+                status: status.as_u16(),
+                body,
+            },
+        }
+    }
+
+    /// Whether the same request may succeed if sent again.
+    pub fn is_retryable(&self) -> bool {
+        match self.last_error() {
+            Error::Timeout { .. }
+            | Error::Connect { .. }
+            | Error::Transport { .. }
+            | Error::ServerError { .. } => true,
+            Error::UnexpectedStatus { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_connect(&self) -> bool {
+        matches!(self.last_error(), Error::Connect { .. })
     }
-    types {
-        Error, ErrorKind, ResultExt, Result;
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self.last_error(), Error::Unauthorized { .. })
+    }
+
+    /// Number of times the failed request was sent.
+    pub fn attempts(&self) -> u32 {
+        match self {
+            Error::RetriesExhausted { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+
+    /// The error of the last attempt.
+    pub fn last_error(&self) -> &Error {
+        match self {
+            Error::RetriesExhausted { last_error, .. } => last_error,
+            error => error,
+        }
+    }
+}
+
+impl From<&ResponseModel> for Error {
+    fn from(response: &ResponseModel) -> Self {
+        Error::ApiError {
+            status: response.status.clone(),
+            code: response.code,
+            message: response.message.clone(),
+        }
     }
+}
+
+impl From<Vec<FieldError>> for Error {
+    fn from(errors: Vec<FieldError>) -> Self {
+        Error::Validation(errors)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serialize(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout { method, url } => write!(f, "Timeout. Url: {method:?} {url}"),
+            Error::Connect { url, message } => {
+                write!(f, "Failed to connect. Url: {url}. {message}")
+            }
+            Error::Transport { url, message } => {
+                write!(f, "Transport error. Url: {url}. {message}")
+            }
+            Error::Unauthorized { url } => write!(f, "Unauthorized or forbidden. Url: {url}"),
+            Error::BadRequest { url, body } => {
+                write!(f, "Received bad request status. Url: {url}. Response: {body:?}")
+            }
+            Error::ServerError { url, status, body } => write!(
+                f,
+                "Received server error status {status}. Url: {url}. Response: {body:?}"
+            ),
+            Error::UnexpectedStatus { url, status, body } => write!(
+                f,
+                "Received response code: {status}. Url: {url}. Response: {body:?}"
+            ),
+            Error::InvalidStatus { url, status } => {
+                write!(f, "Received invalid status code: {status}. Url: {url}")
+            }
+            Error::ApiError {
+                status,
+                code,
+                message,
+            } => write!(f, "BridgerPay error {status} {code}: {message}"),
+            Error::Deserialize { url, body, message } => write!(
+                f,
+                "Failed to deserialize. Url: {url}. {message}. Body: {body}"
+            ),
+            Error::Serialize(message) => write!(f, "Failed to serialize request: {message}"),
+            Error::Validation(errors) => {
+                let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "Invalid request: {}", errors.join("; "))
+            }
+            Error::NotLoggedIn => write!(f, "Not logged in"),
+            Error::RetriesExhausted {
+                attempts,
+                last_error,
+            } => write!(f, "Failed after {attempts} attempts: {last_error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use http::StatusCode;
+
+    #[test]
+    fn classifies_status_codes() {
+        let error = Error::from_status("url".into(), StatusCode::FORBIDDEN, "".into());
+        assert!(error.is_unauthorized());
+        assert!(!error.is_retryable());
+
+        let error = Error::from_status("url".into(), StatusCode::SERVICE_UNAVAILABLE, "".into());
+        assert!(matches!(error, Error::ServerError { status: 503, .. }));
+        assert!(error.is_retryable());
 
-    foreign_links {
-        IoError(std::io::Error);
-        ParseFloatError(std::num::ParseFloatError);
-        Json(serde_json::Error);
-        TimestampError(std::time::SystemTimeError);
+        let error = Error::from_status("url".into(), StatusCode::BAD_REQUEST, "body".into());
+        assert!(matches!(error, Error::BadRequest { ref body, .. } if body == "body"));
+        assert!(!error.is_retryable());
     }
 }
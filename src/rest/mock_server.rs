@@ -0,0 +1,281 @@
+//! In-process BridgerPay API emulation for offline integration tests.
+//!
+//! Responses use the real `Response<T>` envelope. `/v2/auth/login` and `/v2/auth/refresh_token`
+//! issue tokens, every other path requires a valid `Authorization: Bearer` token. Endpoints
+//! without a built-in handler answer with the result configured via
+//! [`MockBridgerPayServer::set_result`].
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const LOGIN_PATH: &str = "/v2/auth/login";
+const REFRESH_TOKEN_PATH: &str = "/v2/auth/refresh_token";
+const CREATE_CASHIER_SESSION_PATH: &str = "/v2/cashier/session/create";
+
+#[derive(Debug, Clone)]
+pub enum MockFailure {
+    /// Responds with the status code and an empty body.
+    Status(u16),
+    /// Waits before handling the request normally.
+    Delay(Duration),
+    /// Responds with 200 and a body that is not valid JSON.
+    MalformedJson,
+    /// Responds with 200 and `response.status` other than `OK`.
+    ApiError { code: i32, message: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query string.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Path prefix and the `result` returned for it.
+    results: Mutex<Vec<(String, Value)>>,
+    /// Path prefix and a failure applied to the next matching request.
+    failures: Mutex<VecDeque<(String, MockFailure)>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    tokens: Mutex<HashSet<String>>,
+    token_lifetime: AtomicU64,
+    counter: AtomicU64,
+}
+
+impl MockState {
+    fn take_failure(&self, path: &str) -> Option<MockFailure> {
+        let mut failures = self.failures.lock().unwrap();
+        let index = failures
+            .iter()
+            .position(|(prefix, _)| path.starts_with(prefix.as_str()))?;
+
+        failures.remove(index).map(|(_, failure)| failure)
+    }
+
+    fn get_result(&self, path: &str) -> Option<Value> {
+        self.results
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, result)| result.clone())
+    }
+
+    fn next_id(&self) -> u64 {
+        self.counter.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn issue_token(&self) -> Value {
+        let id = self.next_id();
+        let token = format!("access-token-{id}");
+        self.tokens.lock().unwrap().insert(token.clone());
+
+        json!({
+            "refresh_token": format!("refresh-token-{id}"),
+            "access_token": {
+                "token": token,
+                "expires_in": self.token_lifetime.load(Ordering::SeqCst),
+            },
+        })
+    }
+
+    fn is_authorized(&self, request: &RecordedRequest) -> bool {
+        let Some(token) = request
+            .get_header("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return false;
+        };
+
+        self.tokens.lock().unwrap().contains(token)
+    }
+}
+
+pub struct MockBridgerPayServer {
+    url: String,
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockBridgerPayServer {
+    /// Starts the server on a random local port. It stops when dropped.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(MockState::default());
+        state.token_lifetime.store(3600, Ordering::SeqCst);
+
+        let router = Router::new()
+            .fallback(handle_request)
+            .with_state(state.clone());
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_signal.await;
+                })
+                .await;
+        });
+
+        Ok(Self {
+            url,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Base url to return from `RestApiConfig::get_api_url`.
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sets the `result` returned for requests whose path starts with `path`.
+    /// Overrides the built-in handlers.
+    pub fn set_result(&self, path: &str, result: Value) {
+        self.state
+            .results
+            .lock()
+            .unwrap()
+            .push((path.to_string(), result));
+    }
+
+    /// Fails the next request whose path starts with `path`. Failures are applied in the order
+    /// they were added.
+    pub fn fail_next(&self, path: &str, failure: MockFailure) {
+        self.state
+            .failures
+            .lock()
+            .unwrap()
+            .push_back((path.to_string(), failure));
+    }
+
+    /// Sets `expires_in` of the tokens issued from now on.
+    pub fn set_token_lifetime(&self, lifetime: Duration) {
+        self.state
+            .token_lifetime
+            .store(lifetime.as_secs(), Ordering::SeqCst);
+    }
+
+    /// Revokes all issued tokens so the next authorized request gets 401.
+    pub fn revoke_tokens(&self) {
+        self.state.tokens.lock().unwrap().clear();
+    }
+
+    pub fn get_requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    pub fn get_requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.get_requests()
+            .into_iter()
+            .filter(|request| request.path.starts_with(path))
+            .collect()
+    }
+}
+
+impl Drop for MockBridgerPayServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn handle_request(State(state): State<Arc<MockState>>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.to_string())
+        .unwrap_or_default();
+    let recorded = RecordedRequest {
+        method: parts.method.to_string(),
+        path: path.clone(),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    value.to_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+    };
+    state.requests.lock().unwrap().push(recorded.clone());
+
+    match state.take_failure(&path) {
+        Some(MockFailure::Status(status)) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return status.into_response();
+        }
+        Some(MockFailure::Delay(delay)) => tokio::time::sleep(delay).await,
+        Some(MockFailure::MalformedJson) => return json_response("{\"response\": {"),
+        Some(MockFailure::ApiError { code, message }) => {
+            let body = json!({
+                "response": { "status": "ERROR", "code": code, "message": message },
+                "result": null,
+            });
+            return json_response(&body.to_string());
+        }
+        None => {}
+    }
+
+    let is_auth = path.starts_with(LOGIN_PATH) || path.starts_with(REFRESH_TOKEN_PATH);
+
+    if !is_auth && !state.is_authorized(&recorded) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let result = if let Some(result) = state.get_result(&path) {
+        result
+    } else if is_auth {
+        state.issue_token()
+    } else if path.starts_with(CREATE_CASHIER_SESSION_PATH) {
+        json!({ "cashier_token": format!("cashier-token-{}", state.next_id()) })
+    } else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let body = json!({
+        "response": { "status": "OK", "code": 200, "message": "" },
+        "result": result,
+    });
+
+    json_response(&body.to_string())
+}
+
+fn json_response(body: &str) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -1,5 +1,12 @@
 pub mod api_client;
+pub mod builders;
+pub mod credentials;
 pub mod endpoints;
 pub mod errors;
+#[cfg(feature = "mock-server")]
+pub mod mock_server;
 pub mod models;
+pub mod retry;
+pub mod transport;
+pub mod validation;
 pub use models::*;
@@ -1,3 +1,5 @@
+use crate::money::Amount;
+use crate::webhook::{Charge, ChargeAttributesStatus, ChargeOperationType};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +14,11 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub response: ResponseModel,
@@ -27,6 +34,7 @@ pub struct LoginModel {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessTokenModel {
     pub token: String,
+    /// Token lifetime in seconds.
     pub expires_in: i64,
 }
 
@@ -48,7 +56,7 @@ pub struct CreateCashierSessionRequest {
     /// The transaction will be created in the country specified, following ISO 3166-1 - Country Codes (e.g., "US," "CN," or "BE").
     pub country: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub amount: Option<f64>,
+    pub amount: Option<Amount>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub theme: Option<CheckoutTheme>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -127,3 +135,234 @@ pub struct CreateCashierSessionResponse {
 pub struct CashierSessionModel {
     pub cashier_token: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashierSessionStatusModel {
+    pub cashier_token: String,
+    pub status: CashierSessionStatus,
+    pub order_id: Option<String>,
+    /// Charges made in the session so far.
+    #[serde(default)]
+    pub charges: Vec<Charge>,
+    pub created_at: Option<u64>,
+    pub expires_at: Option<u64>,
+}
+
+#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+pub enum CashierSessionStatus {
+    #[strum(to_string = "open")]
+    #[serde(rename = "open")]
+    Open,
+    #[strum(to_string = "closed")]
+    #[serde(rename = "closed")]
+    Closed,
+    #[strum(to_string = "expired")]
+    #[serde(rename = "expired")]
+    Expired,
+    #[strum(to_string = "cancelled")]
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+/// Hosted checkout page for the session that can be shared by email or chat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePaymentLinkRequest {
+    /// Order and customer prefill of the checkout page.
+    #[serde(flatten)]
+    pub session: CreateCashierSessionRequest,
+    /// Unix time in seconds after which the link can't be paid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Whether the link expires after the first approved payment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub single_use: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentLinkModel {
+    pub payment_link_id: String,
+    pub url: String,
+    pub status: PaymentLinkStatus,
+    pub order_id: Option<String>,
+    pub expires_at: Option<u64>,
+    pub single_use: Option<bool>,
+}
+
+#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+pub enum PaymentLinkStatus {
+    #[strum(to_string = "active")]
+    #[serde(rename = "active")]
+    Active,
+    #[strum(to_string = "paid")]
+    #[serde(rename = "paid")]
+    Paid,
+    #[strum(to_string = "expired")]
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionModel {
+    pub order_id: String,
+    pub psp_name: Option<String>,
+    pub charge: Charge,
+}
+
+/// Filters of the transaction listing. Unset filters match every transaction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListTransactionsRequest {
+    /// Unix time in seconds, inclusive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+    /// Unix time in seconds, exclusive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ChargeAttributesStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub psp_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_type: Option<ChargeOperationType>,
+    /// Page number starting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionsPageModel {
+    pub transactions: Vec<TransactionModel>,
+    pub page: u32,
+    pub total_pages: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundRequest {
+    /// BridgerPay id of the charge to refund.
+    pub charge_id: String,
+    /// Refunded amount in the charge currency. The whole charge is refunded when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRequest {
+    /// BridgerPay id of the authorized charge.
+    pub charge_id: String,
+    /// Captured amount in the charge currency. The whole authorized amount is captured when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoidRequest {
+    /// BridgerPay id of the authorized charge.
+    pub charge_id: String,
+}
+
+/// Merchant initiated charge of a card stored by a previous deposit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeTokenRequest {
+    /// The Cashier key refers to software-level credentials utilized for the purpose of identifying a merchant.
+    pub cashier_key: Option<String>,
+    /// The Order ID denotes the unique transaction identifier within the merchant's system.
+    pub order_id: String,
+    /// Token from `ChargeAttributes::credit_card_token` or the `payment_card_token` webhook.
+    pub credit_card_token: String,
+    pub amount: Amount,
+    /// ISO 4217 currency of the charge.
+    pub currency: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_recurring: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Returned as is in the charge notifications.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    /// The platform ID refers to the unique ID from the merchant's CRM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePayoutRequest {
+    /// The Cashier key refers to software-level credentials utilized for the purpose of identifying a merchant.
+    pub cashier_key: Option<String>,
+    /// The Order ID denotes the unique payout identifier within the merchant's system.
+    pub order_id: String,
+    /// ISO 4217 currency of the payout.
+    pub currency: String,
+    /// ISO 3166-1 alpha-2 country of the customer.
+    pub country: String,
+    pub amount: Amount,
+    /// Token of a card stored by a previous deposit. Either this or `wire_transfer` must be set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_card_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wire_transfer: Option<WireTransferDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    /// Returned as is in the payout notifications.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    /// The platform ID refers to the unique ID from the merchant's CRM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireTransferDetails {
+    pub beneficiary_name: String,
+    /// Either this or `account_number` must be set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iban: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swift_code: Option<String>,
+    /// ISO 3166-1 alpha-2 country of the bank.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_country: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutModel {
+    pub payout_id: String,
+    pub order_id: String,
+    pub status: PayoutStatus,
+    pub amount: Option<Amount>,
+    pub currency: Option<String>,
+    pub decline_reason: Option<String>,
+    pub created_at: Option<u64>,
+}
+
+#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+pub enum PayoutStatus {
+    #[strum(to_string = "pending")]
+    #[serde(rename = "pending")]
+    Pending,
+    #[strum(to_string = "processing")]
+    #[serde(rename = "processing")]
+    Processing,
+    #[strum(to_string = "approved")]
+    #[serde(rename = "approved")]
+    Approved,
+    #[strum(to_string = "declined")]
+    #[serde(rename = "declined")]
+    Declined,
+    #[strum(to_string = "cancelled")]
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
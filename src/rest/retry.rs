@@ -0,0 +1,139 @@
+use crate::rest::endpoints::RestApiEndpoint;
+use crate::rest::errors::Error;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashSet;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts including the first one.
+    pub max_attempts: u32,
+    /// Backoff before the second attempt, doubled for every next one.
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Share of the backoff that is randomized, from 0.0 (none) to 1.0 (full jitter).
+    pub jitter: f64,
+    /// Endpoints that are sent again after any retryable error. Others are only sent again when
+    /// the request never reached the server. Defaults to [`RestApiEndpoint::is_idempotent`].
+    pub idempotent_endpoints: HashSet<RestApiEndpoint>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+            idempotent_endpoints: RestApiEndpoint::iter()
+                .filter(|endpoint| endpoint.is_idempotent())
+                .collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Overrides whether the endpoint is idempotent, e.g. for an endpoint BridgerPay deduplicates
+    /// by `order_id`.
+    pub fn with_idempotent(mut self, endpoint: RestApiEndpoint, is_idempotent: bool) -> Self {
+        if is_idempotent {
+            self.idempotent_endpoints.insert(endpoint);
+        } else {
+            self.idempotent_endpoints.remove(&endpoint);
+        }
+
+        self
+    }
+
+    /// Whether a request that failed on the given attempt (starting from 1) should be sent again.
+    /// Endpoints that are not idempotent are only retried when the request never reached the server.
+    pub fn should_retry(&self, endpoint: &RestApiEndpoint, error: &Error, attempt: u32) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        if self.idempotent_endpoints.contains(endpoint) {
+            error.is_retryable()
+        } else {
+            error.is_connect()
+        }
+    }
+
+    /// Delay before the attempt following the given one.
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let backoff = self
+            .base_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+
+        backoff.mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+fn random_fraction() -> f64 {
+    let mut bytes = [0u8; 4];
+
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 0.0;
+    }
+
+    u32::from_le_bytes(bytes) as f64 / u32::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use crate::rest::endpoints::RestApiEndpoint;
+    use crate::rest::errors::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.get_backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.get_backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.get_backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.get_backoff(4), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn non_idempotent_endpoints_retry_only_connect_failures() {
+        let policy = RetryPolicy::default();
+        let timeout = Error::Timeout {
+            method: http::Method::POST,
+            url: "url".into(),
+        };
+        let connect = Error::Connect {
+            url: "url".into(),
+            message: "refused".into(),
+        };
+
+        assert!(policy.should_retry(&RestApiEndpoint::AuthLogin, &timeout, 1));
+        assert!(!policy.should_retry(&RestApiEndpoint::AuthLogin, &timeout, 3));
+        assert!(!policy.should_retry(&RestApiEndpoint::CreateCashierSession, &timeout, 1));
+        assert!(policy.should_retry(&RestApiEndpoint::CreateCashierSession, &connect, 1));
+
+        let policy = RetryPolicy::default()
+            .with_idempotent(RestApiEndpoint::CreateCashierSession, true)
+            .with_idempotent(RestApiEndpoint::AuthLogin, false);
+        assert!(policy.should_retry(&RestApiEndpoint::CreateCashierSession, &timeout, 1));
+        assert!(!policy.should_retry(&RestApiEndpoint::AuthLogin, &timeout, 1));
+    }
+}
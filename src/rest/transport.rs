@@ -0,0 +1,185 @@
+use flurl::FlUrl;
+use http::Method;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransportError {
+    /// The request never reached the server, e.g. on a DNS, TCP connect or TLS handshake failure.
+    Connect(String),
+    /// Any other failure. The request may have reached the server, e.g. when the connection
+    /// broke after it was written.
+    Receive(String),
+}
+
+#[async_trait::async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FlUrlTransport;
+
+#[async_trait::async_trait]
+impl HttpTransport for FlUrlTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut flurl = FlUrl::new(&request.url);
+
+        for (name, value) in request.headers {
+            flurl = flurl.with_header(name, value);
+        }
+
+        let method = request.method;
+        let result = if method == Method::GET {
+            flurl.get().await
+        } else if method == Method::POST {
+            flurl.post(request.body).await
+        } else if method == Method::PUT {
+            flurl.put(request.body).await
+        } else if method == Method::PATCH {
+            flurl.patch(request.body).await
+        } else if method == Method::DELETE {
+            flurl.delete().await
+        } else {
+            return Err(TransportError::Connect(format!("Method {method} is not supported")));
+        };
+
+        let response = result.map_err(|err| {
+            let message = format!("{err:?}");
+
+            if is_connect_error(&message) {
+                TransportError::Connect(message)
+            } else {
+                TransportError::Receive(message)
+            }
+        })?;
+        let status = response.get_status_code();
+        let body = response
+            .receive_body()
+            .await
+            .map_err(|err| TransportError::Receive(format!("{err:?}")))?;
+
+        Ok(HttpResponse { status, body })
+    }
+}
+
+/// Whether the flurl error happened before the request was written. flurl errors carry no such
+/// flag, so anything not recognized is treated as possibly sent.
+fn is_connect_error(message: &str) -> bool {
+    const PATTERNS: [&str; 9] = [
+        "dns error",
+        "failed to lookup address",
+        "connection refused",
+        "connect error",
+        "connecterror",
+        "no route to host",
+        "network is unreachable",
+        "handshake",
+        "certificate",
+    ];
+    let message = message.to_lowercase();
+
+    PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+type Handler = dyn Fn(&HttpRequest) -> Result<HttpResponse, TransportError> + Send + Sync;
+
+/// Answers requests with a handler function instead of the network. Every request is recorded.
+pub struct InMemoryTransport {
+    handler: Box<Handler>,
+    delay: Option<Duration>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl InMemoryTransport {
+    pub fn new(
+        handler: impl Fn(&HttpRequest) -> Result<HttpResponse, TransportError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Box::new(handler),
+            delay: None,
+            requests: Default::default(),
+        }
+    }
+
+    /// Delays every response, e.g. to test timeouts or concurrent requests.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn get_requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpTransport for InMemoryTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        self.requests.lock().unwrap().push(request.clone());
+
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        (self.handler)(&request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_connect_error;
+
+    #[test]
+    fn classifies_flurl_errors_before_sending_as_connect() {
+        // one message per pattern, so that every pattern is pinned
+        let connect_errors = [
+            "Dns error: no record found for example.com",
+            "failed to lookup address information: Name or service not known",
+            "Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }",
+            "hyper::Error(Connect, \"tcp connect error\")",
+            "ConnectError { kind: TimedOut }",
+            "No route to host (os error 113)",
+            "Network is unreachable (os error 101)",
+            "TLS handshake eof",
+            "invalid peer certificate: UnknownIssuer",
+        ];
+        let receive_errors = [
+            "hyper::Error(IncompleteMessage)",
+            "Connection reset by peer (os error 104)",
+            "Broken pipe (os error 32)",
+            "error reading a body from connection",
+        ];
+
+        for message in connect_errors {
+            assert!(is_connect_error(message), "{message}");
+        }
+
+        for message in receive_errors {
+            assert!(!is_connect_error(message), "{message}");
+        }
+    }
+}
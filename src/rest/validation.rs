@@ -0,0 +1,444 @@
+use crate::iso::{is_country_code, is_currency_code};
+use crate::money::Amount;
+use crate::rest::{
+    CaptureRequest, ChargeTokenRequest, CreateCashierSessionRequest, CreatePaymentLinkRequest,
+    CreatePayoutRequest, TransactionModel,
+};
+use crate::webhook::{ChargeAttributesStatus, ChargeOperationType};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_ID_LEN: usize = 128;
+const MAX_NAME_LEN: usize = 100;
+const MAX_EMAIL_LEN: usize = 254;
+const MAX_PHONE_LEN: usize = 32;
+const MAX_ZIP_CODE_LEN: usize = 16;
+const MAX_ADDRESS_LEN: usize = 255;
+const MAX_LANGUAGE_LEN: usize = 10;
+const MAX_PAYLOAD_LEN: usize = 8192;
+const MAX_ACCOUNT_LEN: usize = 34;
+const MAX_SWIFT_CODE_LEN: usize = 11;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Collects field errors of a request model.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn add_error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn required(&mut self, field: &str, value: &str, max_len: usize) {
+        if value.trim().is_empty() {
+            self.add_error(field, "must not be empty");
+        } else {
+            self.max_len(field, Some(value), max_len);
+        }
+    }
+
+    pub fn max_len(&mut self, field: &str, value: Option<&str>, max_len: usize) {
+        if let Some(value) = value {
+            if value.chars().count() > max_len {
+                self.add_error(field, format!("must be at most {max_len} characters"));
+            }
+        }
+    }
+
+    pub fn currency(&mut self, field: &str, value: &str) {
+        if !is_currency_code(value) {
+            self.add_error(field, format!("{value:?} is not an ISO 4217 currency code"));
+        }
+    }
+
+    pub fn country(&mut self, field: &str, value: &str) {
+        if !is_country_code(value) {
+            self.add_error(
+                field,
+                format!("{value:?} is not an ISO 3166-1 alpha-2 country code"),
+            );
+        }
+    }
+
+    pub fn amount(&mut self, field: &str, value: Option<Amount>, currency: &str) {
+        if let Some(value) = value {
+            if value.is_negative() {
+                self.add_error(field, format!("{value} must not be negative"));
+            } else if is_currency_code(currency) && value.to_minor_units(currency).is_none() {
+                self.add_error(field, format!("{value} is not a valid {currency} amount"));
+            }
+        }
+    }
+
+    pub fn email(&mut self, field: &str, value: Option<&str>) {
+        if let Some(value) = value {
+            if !is_valid_email(value) {
+                self.add_error(field, format!("{value:?} is not a valid email"));
+            } else {
+                self.max_len(field, Some(value), MAX_EMAIL_LEN);
+            }
+        }
+    }
+
+    pub fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+fn is_valid_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && local.len() <= 64
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with(['.', '-'])
+        && !domain.ends_with(['.', '-'])
+        && !domain.contains("..")
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+impl CreateCashierSessionRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::default();
+        self.validate_fields(&mut validator);
+
+        validator.finish()
+    }
+
+    fn validate_fields(&self, validator: &mut Validator) {
+        validator.max_len("cashier_key", self.cashier_key.as_deref(), MAX_ID_LEN);
+        validator.required("order_id", &self.order_id, MAX_ID_LEN);
+        validator.currency("currency", &self.currency);
+        validator.country("country", &self.country);
+        validator.amount("amount", self.amount, &self.currency);
+        validator.max_len("first_name", self.first_name.as_deref(), MAX_NAME_LEN);
+        validator.max_len("last_name", self.last_name.as_deref(), MAX_NAME_LEN);
+        validator.max_len("phone", self.phone.as_deref(), MAX_PHONE_LEN);
+        validator.email("email", self.email.as_deref());
+        validator.max_len("zip_code", self.zip_code.as_deref(), MAX_ZIP_CODE_LEN);
+        validator.max_len("payload", self.payload.as_deref(), MAX_PAYLOAD_LEN);
+        validator.max_len("platform_id", self.platform_id.as_deref(), MAX_ID_LEN);
+        validator.max_len("tracking_id", self.tracking_id.as_deref(), MAX_ID_LEN);
+        validator.max_len("affiliate_id", self.affiliate_id.as_deref(), MAX_ID_LEN);
+        validator.max_len("city", self.city.as_deref(), MAX_NAME_LEN);
+        validator.max_len("address", self.address.as_deref(), MAX_ADDRESS_LEN);
+        validator.max_len("state", self.state.as_deref(), MAX_NAME_LEN);
+        validator.max_len("language", self.language.as_deref(), MAX_LANGUAGE_LEN);
+        validator.max_len("button_text", self.button_text.as_deref(), MAX_NAME_LEN);
+        validator.max_len(
+            "deposit_button_text",
+            self.deposit_button_text.as_deref(),
+            MAX_NAME_LEN,
+        );
+    }
+}
+
+impl CreatePaymentLinkRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::default();
+        self.session.validate_fields(&mut validator);
+
+        if let Some(expires_at) = self.expires_at {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default();
+
+            if expires_at <= now {
+                validator.add_error("expires_at", format!("{expires_at} is not in the future"));
+            }
+        }
+
+        validator.finish()
+    }
+}
+
+impl CaptureRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::default();
+        validator.required("charge_id", &self.charge_id, MAX_ID_LEN);
+
+        if let Some(amount) = self.amount {
+            if amount.is_negative() || amount.is_zero() {
+                validator.add_error("amount", format!("{amount} must be positive"));
+            }
+        }
+
+        validator.finish()
+    }
+}
+
+impl ChargeTokenRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::default();
+        validator.max_len("cashier_key", self.cashier_key.as_deref(), MAX_ID_LEN);
+        validator.required("order_id", &self.order_id, MAX_ID_LEN);
+        validator.required("credit_card_token", &self.credit_card_token, MAX_ID_LEN);
+        validator.currency("currency", &self.currency);
+        validator.amount("amount", Some(self.amount), &self.currency);
+
+        if self.amount.is_zero() {
+            validator.add_error("amount", "must be positive");
+        }
+
+        validator.max_len("description", self.description.as_deref(), MAX_ADDRESS_LEN);
+        validator.max_len("payload", self.payload.as_deref(), MAX_PAYLOAD_LEN);
+        validator.max_len("platform_id", self.platform_id.as_deref(), MAX_ID_LEN);
+
+        validator.finish()
+    }
+}
+
+impl CreatePayoutRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::default();
+        validator.max_len("cashier_key", self.cashier_key.as_deref(), MAX_ID_LEN);
+        validator.required("order_id", &self.order_id, MAX_ID_LEN);
+        validator.currency("currency", &self.currency);
+        validator.country("country", &self.country);
+        validator.amount("amount", Some(self.amount), &self.currency);
+
+        if self.amount.is_zero() {
+            validator.add_error("amount", "must be positive");
+        }
+
+        match (&self.credit_card_token, &self.wire_transfer) {
+            (Some(token), None) => validator.required("credit_card_token", token, MAX_ID_LEN),
+            (None, Some(wire)) => {
+                validator.required(
+                    "wire_transfer.beneficiary_name",
+                    &wire.beneficiary_name,
+                    MAX_NAME_LEN,
+                );
+                validator.max_len("wire_transfer.iban", wire.iban.as_deref(), MAX_ACCOUNT_LEN);
+                validator.max_len(
+                    "wire_transfer.account_number",
+                    wire.account_number.as_deref(),
+                    MAX_ACCOUNT_LEN,
+                );
+                validator.max_len(
+                    "wire_transfer.bank_name",
+                    wire.bank_name.as_deref(),
+                    MAX_NAME_LEN,
+                );
+                validator.max_len(
+                    "wire_transfer.swift_code",
+                    wire.swift_code.as_deref(),
+                    MAX_SWIFT_CODE_LEN,
+                );
+
+                if wire.iban.is_none() && wire.account_number.is_none() {
+                    validator.add_error("wire_transfer", "iban or account_number is required");
+                }
+
+                if let Some(bank_country) = &wire.bank_country {
+                    validator.country("wire_transfer.bank_country", bank_country);
+                }
+            }
+            _ => validator.add_error(
+                "credit_card_token",
+                "exactly one of credit_card_token and wire_transfer is required",
+            ),
+        }
+
+        validator.max_len("first_name", self.first_name.as_deref(), MAX_NAME_LEN);
+        validator.max_len("last_name", self.last_name.as_deref(), MAX_NAME_LEN);
+        validator.email("email", self.email.as_deref());
+        validator.max_len("phone", self.phone.as_deref(), MAX_PHONE_LEN);
+        validator.max_len("payload", self.payload.as_deref(), MAX_PAYLOAD_LEN);
+        validator.max_len("platform_id", self.platform_id.as_deref(), MAX_ID_LEN);
+
+        validator.finish()
+    }
+}
+
+/// Checks a refund of `original` against the refunds already made for the same order and
+/// returns the amount to refund. Without `amount` the whole remaining amount is refunded.
+///
+/// Refunds can't be matched to a specific charge of the order, so every pending or approved
+/// refund of the order counts against `original`.
+pub fn validate_refund(
+    original: &TransactionModel,
+    order_transactions: &[TransactionModel],
+    amount: Option<Amount>,
+) -> Result<Amount, Vec<FieldError>> {
+    let mut validator = Validator::default();
+    let charge = &original.charge;
+    let attributes = &charge.attributes;
+
+    if charge.operation_type == Some(ChargeOperationType::Refund) {
+        validator.add_error("charge_id", "a refund can't be refunded");
+    }
+
+    if charge.is_refundable == Some(false) {
+        validator.add_error("charge_id", "charge is not refundable");
+    }
+
+    if attributes.status != ChargeAttributesStatus::Approved {
+        validator.add_error(
+            "charge_id",
+            format!(
+                "only approved charges can be refunded, got {}",
+                attributes.status
+            ),
+        );
+    }
+
+    let Some(charged) = attributes.amount else {
+        validator.add_error("charge_id", "charge has no amount");
+        return Err(validator.finish().unwrap_err());
+    };
+
+    let mut refunded = Amount::ZERO;
+
+    for transaction in order_transactions {
+        let refund = &transaction.charge;
+        let is_refund = refund.operation_type == Some(ChargeOperationType::Refund);
+        let is_cancelled = matches!(
+            refund.attributes.status,
+            ChargeAttributesStatus::Declined | ChargeAttributesStatus::Voided
+        );
+
+        if is_refund && !is_cancelled {
+            let refund_amount = refund.attributes.amount.unwrap_or(Amount::ZERO);
+            refunded = refunded.checked_add(refund_amount).unwrap_or(charged);
+        }
+    }
+
+    let remaining = charged.checked_sub(refunded).unwrap_or(Amount::ZERO);
+    let amount = amount.unwrap_or(remaining);
+
+    if amount.is_negative() || amount.is_zero() {
+        validator.add_error("amount", format!("{amount} must be positive"));
+    } else if amount > remaining {
+        validator.add_error(
+            "amount",
+            format!("{amount} exceeds the refundable {remaining} ({refunded} of {charged} already refunded)"),
+        );
+    }
+
+    if let Some(currency) = &attributes.currency {
+        validator.amount("amount", Some(amount), currency);
+    }
+
+    validator.finish()?;
+
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_refund;
+    use crate::money::Amount;
+    use crate::rest::{CreateCashierSessionRequest, CreatePayoutRequest, TransactionModel};
+
+    fn transaction(operation_type: &str, status: &str, amount: &str) -> TransactionModel {
+        serde_json::from_value(serde_json::json!({
+            "order_id": "order-1",
+            "charge": {
+                "type": "charge",
+                "operation_type": operation_type,
+                "attributes": {
+                    "amount": amount,
+                    "currency": "USD",
+                    "status": status,
+                    "created_at": 1,
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn builds_valid_request() {
+        let request = CreateCashierSessionRequest::builder("order-1", "USD", "NL")
+            .amount("10.5".parse().unwrap())
+            .email("john@example.com")
+            .first_name("John")
+            .build()
+            .unwrap();
+
+        assert_eq!(request.amount, Some(Amount::new(105, 1).unwrap()));
+        assert_eq!(request.email.as_deref(), Some("john@example.com"));
+    }
+
+    #[test]
+    fn collects_field_errors() {
+        let errors = CreateCashierSessionRequest::builder("", "XXX", "Netherlands")
+            .amount(Amount::from(-1))
+            .email("john.example.com")
+            .first_name("J".repeat(101))
+            .build()
+            .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                "order_id",
+                "currency",
+                "country",
+                "amount",
+                "first_name",
+                "email"
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_one_payout_destination() {
+        let builder = CreatePayoutRequest::builder("payout-1", "EUR", "DE", Amount::from(100));
+
+        assert!(builder.clone().credit_card_token("token").build().is_ok());
+
+        let errors = builder.build().unwrap_err();
+        assert_eq!(errors[0].field, "credit_card_token");
+    }
+
+    #[test]
+    fn limits_partial_refunds_to_charged_amount() {
+        let original = transaction("deposit", "approved", "100");
+        let order = vec![
+            original.clone(),
+            transaction("refund", "approved", "30"),
+            transaction("refund", "declined", "50"),
+            transaction("refund", "approved_on_hold", "20.5"),
+        ];
+
+        let remaining = validate_refund(&original, &order, None).unwrap();
+        assert_eq!(remaining, "49.5".parse().unwrap());
+
+        let partial = validate_refund(&original, &order, Some("49.5".parse().unwrap()));
+        assert!(partial.is_ok());
+
+        let errors = validate_refund(&original, &order, Some(Amount::from(50))).unwrap_err();
+        assert_eq!(errors[0].field, "amount");
+
+        let errors =
+            validate_refund(&original, &order, Some("0.001".parse().unwrap())).unwrap_err();
+        assert_eq!(errors[0].field, "amount");
+    }
+}
@@ -0,0 +1,320 @@
+use crate::webhook::event::WebhookEvent;
+use crate::webhook::handler::WebhookHandlerError;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Default time after which a webhook still in progress, e.g. because the request was cancelled,
+/// is handled again.
+pub const DEFAULT_IN_PROGRESS_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupState {
+    /// Seen for the first time, now marked as in progress.
+    New,
+    /// Being handled by another request.
+    InProgress,
+    /// Handled successfully.
+    Done,
+}
+
+/// Remembers processed webhooks so the retries of BridgerPay are handled once.
+#[async_trait::async_trait]
+pub trait WebhookDedupStore: Send + Sync {
+    /// Marks the key as in progress if it is neither in progress nor done. Returns the state
+    /// before the call.
+    async fn try_start(&self, key: &str) -> Result<DedupState, WebhookHandlerError>;
+
+    /// Marks the key as done after the webhook was handled.
+    async fn finish(&self, key: &str) -> Result<(), WebhookHandlerError>;
+
+    /// Removes the in progress mark, e.g. when processing failed and the webhook has to be
+    /// handled again.
+    async fn unmark(&self, key: &str) -> Result<(), WebhookHandlerError>;
+}
+
+/// Key of the webhook in a [`WebhookDedupStore`]: the charge id or uuid, or the order id for
+/// events without a charge, plus the webhook type and the cashier session id.
+pub fn get_dedup_key(event: &WebhookEvent) -> String {
+    let id = event
+        .get_charge()
+        .and_then(|charge| charge.id.as_ref().or(charge.uuid.as_ref()))
+        .map(|id| id.as_str())
+        .unwrap_or(event.get_order_id());
+    let cashier_session_id = &event.get_meta().cashier_session_id;
+
+    format!("{id}:{}:{cashier_session_id}", event.get_type())
+}
+
+pub struct InMemoryDedupStore {
+    ttl: Duration,
+    in_progress_timeout: Duration,
+    /// Key, whether it is done and the time it expires at.
+    entries: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl InMemoryDedupStore {
+    /// Keys are forgotten after `ttl`, it should be longer than the BridgerPay retry period.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            in_progress_timeout: DEFAULT_IN_PROGRESS_TIMEOUT,
+            entries: Default::default(),
+        }
+    }
+
+    /// Time after which a key still in progress is handled again.
+    pub fn with_in_progress_timeout(mut self, timeout: Duration) -> Self {
+        self.in_progress_timeout = timeout;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookDedupStore for InMemoryDedupStore {
+    async fn try_start(&self, key: &str) -> Result<DedupState, WebhookHandlerError> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+
+        match entries.get(key) {
+            Some((true, _)) => return Ok(DedupState::Done),
+            Some((false, _)) => return Ok(DedupState::InProgress),
+            None => {}
+        }
+
+        entries.insert(key.to_string(), (false, now + self.in_progress_timeout));
+
+        Ok(DedupState::New)
+    }
+
+    async fn finish(&self, key: &str) -> Result<(), WebhookHandlerError> {
+        let expires_at = Instant::now() + self.ttl;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (true, expires_at));
+
+        Ok(())
+    }
+
+    async fn unmark(&self, key: &str) -> Result<(), WebhookHandlerError> {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some((false, _)) = entries.get(key) {
+            entries.remove(key);
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps the done keys in a file so they survive restarts. The file has one `key expires_at`
+/// line per key, with backslashes and line breaks of the key escaped, and is rewritten on every
+/// change, so it suits a single process only. Keys in progress are kept in memory.
+pub struct FileDedupStore {
+    path: PathBuf,
+    ttl: Duration,
+    in_progress_timeout: Duration,
+    entries: tokio::sync::Mutex<FileEntries>,
+}
+
+#[derive(Default)]
+struct FileEntries {
+    /// Key and the unix time in seconds it expires at.
+    done: HashMap<String, u64>,
+    /// Key and the time it expires at.
+    in_progress: HashMap<String, Instant>,
+}
+
+impl FileDedupStore {
+    /// Loads the keys from `path` if the file exists. Fails with `InvalidData` on a line that
+    /// can't be parsed.
+    pub async fn open(path: impl Into<PathBuf>, ttl: Duration) -> std::io::Result<Self> {
+        let path = path.into();
+        let mut entries = FileEntries::default();
+
+        match tokio::fs::read_to_string(&path).await {
+            Ok(content) => {
+                for (index, line) in content.lines().enumerate() {
+                    let Some((key, expires_at)) = parse_line(line) else {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Invalid line {} of {}: {line}", index + 1, path.display()),
+                        ));
+                    };
+
+                    entries.done.insert(key, expires_at);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        Ok(Self {
+            path,
+            ttl,
+            in_progress_timeout: DEFAULT_IN_PROGRESS_TIMEOUT,
+            entries: tokio::sync::Mutex::new(entries),
+        })
+    }
+
+    /// Time after which a key still in progress is handled again.
+    pub fn with_in_progress_timeout(mut self, timeout: Duration) -> Self {
+        self.in_progress_timeout = timeout;
+        self
+    }
+
+    async fn save(&self, done: &HashMap<String, u64>) -> std::io::Result<()> {
+        let mut content = String::new();
+
+        for (key, expires_at) in done {
+            content.push_str(&format!("{} {expires_at}\n", escape_key(key)));
+        }
+
+        // write then rename, so a crash never leaves a half written file
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, content).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookDedupStore for FileDedupStore {
+    async fn try_start(&self, key: &str) -> Result<DedupState, WebhookHandlerError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let instant = Instant::now();
+        let mut entries = self.entries.lock().await;
+        entries.done.retain(|_, expires_at| *expires_at > now);
+        entries.in_progress.retain(|_, expires_at| *expires_at > instant);
+
+        if entries.done.contains_key(key) {
+            return Ok(DedupState::Done);
+        }
+
+        if entries.in_progress.contains_key(key) {
+            return Ok(DedupState::InProgress);
+        }
+
+        entries
+            .in_progress
+            .insert(key.to_string(), instant + self.in_progress_timeout);
+
+        Ok(DedupState::New)
+    }
+
+    async fn finish(&self, key: &str) -> Result<(), WebhookHandlerError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut entries = self.entries.lock().await;
+        entries.in_progress.remove(key);
+        entries.done.insert(key.to_string(), now + self.ttl.as_secs());
+
+        if let Err(err) = self.save(&entries.done).await {
+            entries.done.remove(key);
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn unmark(&self, key: &str) -> Result<(), WebhookHandlerError> {
+        self.entries.lock().await.in_progress.remove(key);
+
+        Ok(())
+    }
+}
+
+fn escape_key(key: &str) -> String {
+    key.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn parse_line(line: &str) -> Option<(String, u64)> {
+    let (escaped_key, expires_at) = line.rsplit_once(' ')?;
+    let mut key = String::with_capacity(escaped_key.len());
+    let mut chars = escaped_key.chars();
+
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            key.push(character);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => key.push('\\'),
+            'n' => key.push('\n'),
+            'r' => key.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some((key, expires_at.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DedupState, FileDedupStore, InMemoryDedupStore, WebhookDedupStore};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn forgets_keys_after_ttl() {
+        let store = InMemoryDedupStore::new(Duration::from_millis(50))
+            .with_in_progress_timeout(Duration::from_millis(50));
+        let key = "charge-1:approved:session-1";
+
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::New);
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::InProgress);
+        store.finish(key).await.unwrap();
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::Done);
+        let key = "charge-1:declined:session-1";
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::New);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::New);
+        let key = "charge-1:approved:session-1";
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::New);
+    }
+
+    #[tokio::test]
+    async fn keeps_done_keys_in_file() {
+        let path = std::env::temp_dir().join(format!("dedup-{}", uuid::Uuid::new_v4()));
+        let ttl = Duration::from_secs(3600);
+
+        let store = FileDedupStore::open(&path, ttl).await.unwrap();
+        assert_eq!(store.try_start("charge-1:approved:session 1").await.unwrap(), DedupState::New);
+        store.finish("charge-1:approved:session 1").await.unwrap();
+        assert_eq!(store.try_start("charge-2:approved:session-1").await.unwrap(), DedupState::New);
+        store.unmark("charge-2:approved:session-1").await.unwrap();
+        assert_eq!(store.try_start("charge-3:approved:session-1").await.unwrap(), DedupState::New);
+
+        let store = FileDedupStore::open(&path, ttl).await.unwrap();
+        assert_eq!(store.try_start("charge-1:approved:session 1").await.unwrap(), DedupState::Done);
+        assert_eq!(store.try_start("charge-2:approved:session-1").await.unwrap(), DedupState::New);
+        assert_eq!(store.try_start("charge-3:approved:session-1").await.unwrap(), DedupState::New);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn escapes_keys_in_file() {
+        let path = std::env::temp_dir().join(format!("dedup-{}", uuid::Uuid::new_v4()));
+        let ttl = Duration::from_secs(3600);
+        let key = "charge-1\n:approved:\\n\r";
+
+        let store = FileDedupStore::open(&path, ttl).await.unwrap();
+        store.try_start(key).await.unwrap();
+        store.finish(key).await.unwrap();
+        let store = FileDedupStore::open(&path, ttl).await.unwrap();
+        assert_eq!(store.try_start(key).await.unwrap(), DedupState::Done);
+        assert_eq!(store.try_start("charge-1").await.unwrap(), DedupState::New);
+
+        std::fs::write(&path, "charge-1:approved:session-1\n").unwrap();
+        assert!(FileDedupStore::open(&path, ttl).await.is_err());
+        std::fs::write(&path, "charge-1:\\x 100\n").unwrap();
+        assert!(FileDedupStore::open(&path, ttl).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
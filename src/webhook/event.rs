@@ -0,0 +1,238 @@
+use crate::webhook::{Charge, WebhookMeta, WebhookPayload, WebhookType};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+/// Webhook with the data of its `webhook.type`. Deserialized from the same JSON as
+/// [`WebhookPayload`].
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    Approved(ChargeEvent),
+    Declined(ChargeEvent),
+    ApprovedOnHold(ChargeEvent),
+    Authorized(ChargeEvent),
+    Voided(ChargeEvent),
+    CashierSessionInit(SessionEvent),
+    CashierSessionClosed(SessionEvent),
+    PaymentCardToken(TokenEvent),
+    /// Webhook type added to the API after this version of the crate.
+    Unknown(UnknownEvent),
+}
+
+#[derive(Debug, Clone)]
+pub struct ChargeEvent {
+    pub order_id: String,
+    pub psp_name: Option<String>,
+    pub charge: Charge,
+    pub meta: WebhookMeta,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionEvent {
+    pub order_id: String,
+    pub meta: WebhookMeta,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenEvent {
+    pub order_id: String,
+    /// Token to charge the card later with `RestApiClient::charge_token`.
+    pub credit_card_token: String,
+    pub card_masked_number: Option<String>,
+    pub card_brand: Option<String>,
+    pub card_expiration: Option<String>,
+    pub card_holder_name: Option<String>,
+    pub meta: WebhookMeta,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownEvent {
+    pub webhook_type: String,
+    pub order_id: String,
+    pub psp_name: Option<String>,
+    pub charge: Option<Charge>,
+    pub meta: WebhookMeta,
+}
+
+/// Parsed webhook together with the body it was parsed from, e.g. for an audit log.
+#[derive(Debug, Clone)]
+pub struct ReceivedWebhook {
+    pub event: WebhookEvent,
+    pub raw: String,
+}
+
+impl WebhookEvent {
+    pub fn get_type(&self) -> WebhookType {
+        match self {
+            WebhookEvent::Approved(_) => WebhookType::Approved,
+            WebhookEvent::Declined(_) => WebhookType::Declined,
+            WebhookEvent::ApprovedOnHold(_) => WebhookType::ApprovedOnHold,
+            WebhookEvent::Authorized(_) => WebhookType::Authorized,
+            WebhookEvent::Voided(_) => WebhookType::Voided,
+            WebhookEvent::CashierSessionInit(_) => WebhookType::CashierSessionInit,
+            WebhookEvent::CashierSessionClosed(_) => WebhookType::CashierSessionClosed,
+            WebhookEvent::PaymentCardToken(_) => WebhookType::PaymentCardToken,
+            WebhookEvent::Unknown(event) => WebhookType::Unknown(event.webhook_type.clone()),
+        }
+    }
+
+    pub fn get_order_id(&self) -> &str {
+        match self {
+            WebhookEvent::Approved(event)
+            | WebhookEvent::Declined(event)
+            | WebhookEvent::ApprovedOnHold(event)
+            | WebhookEvent::Authorized(event)
+            | WebhookEvent::Voided(event) => &event.order_id,
+            WebhookEvent::CashierSessionInit(event) | WebhookEvent::CashierSessionClosed(event) => {
+                &event.order_id
+            }
+            WebhookEvent::PaymentCardToken(event) => &event.order_id,
+            WebhookEvent::Unknown(event) => &event.order_id,
+        }
+    }
+
+    pub fn get_meta(&self) -> &WebhookMeta {
+        match self {
+            WebhookEvent::Approved(event)
+            | WebhookEvent::Declined(event)
+            | WebhookEvent::ApprovedOnHold(event)
+            | WebhookEvent::Authorized(event)
+            | WebhookEvent::Voided(event) => &event.meta,
+            WebhookEvent::CashierSessionInit(event) | WebhookEvent::CashierSessionClosed(event) => {
+                &event.meta
+            }
+            WebhookEvent::PaymentCardToken(event) => &event.meta,
+            WebhookEvent::Unknown(event) => &event.meta,
+        }
+    }
+
+    pub fn get_charge(&self) -> Option<&Charge> {
+        match self {
+            WebhookEvent::Approved(event)
+            | WebhookEvent::Declined(event)
+            | WebhookEvent::ApprovedOnHold(event)
+            | WebhookEvent::Authorized(event)
+            | WebhookEvent::Voided(event) => Some(&event.charge),
+            WebhookEvent::CashierSessionInit(_) | WebhookEvent::CashierSessionClosed(_) => None,
+            WebhookEvent::PaymentCardToken(_) => None,
+            WebhookEvent::Unknown(event) => event.charge.as_ref(),
+        }
+    }
+
+    /// Parses the body keeping it as received.
+    pub fn parse(raw_body: &[u8]) -> Result<ReceivedWebhook, serde_json::Error> {
+        let event = serde_json::from_slice(raw_body)?;
+        let raw = String::from_utf8_lossy(raw_body).into_owned();
+
+        Ok(ReceivedWebhook { event, raw })
+    }
+}
+
+impl TryFrom<WebhookPayload> for WebhookEvent {
+    type Error = String;
+
+    fn try_from(payload: WebhookPayload) -> Result<Self, Self::Error> {
+        let webhook_type = payload.webhook.webhook_type;
+        let data = payload.data;
+        let meta = payload.meta;
+
+        let charge_event = |charge: Option<Charge>| {
+            let charge = charge.ok_or_else(|| format!("{webhook_type} webhook without charge"))?;
+
+            Ok::<_, String>(ChargeEvent {
+                order_id: data.order_id.clone(),
+                psp_name: data.psp_name.clone(),
+                charge,
+                meta: meta.clone(),
+            })
+        };
+
+        let event = match webhook_type {
+            WebhookType::Approved => WebhookEvent::Approved(charge_event(data.charge)?),
+            WebhookType::Declined => WebhookEvent::Declined(charge_event(data.charge)?),
+            WebhookType::ApprovedOnHold => {
+                WebhookEvent::ApprovedOnHold(charge_event(data.charge)?)
+            }
+            WebhookType::Authorized => WebhookEvent::Authorized(charge_event(data.charge)?),
+            WebhookType::Voided => WebhookEvent::Voided(charge_event(data.charge)?),
+            WebhookType::CashierSessionInit => WebhookEvent::CashierSessionInit(SessionEvent {
+                order_id: data.order_id,
+                meta,
+            }),
+            WebhookType::CashierSessionClosed => {
+                WebhookEvent::CashierSessionClosed(SessionEvent {
+                    order_id: data.order_id,
+                    meta,
+                })
+            }
+            WebhookType::PaymentCardToken => {
+                let error = || format!("{webhook_type} webhook without credit_card_token");
+                let attributes = data.charge.ok_or_else(error)?.attributes;
+                let credit_card_token = attributes.credit_card_token.ok_or_else(error)?;
+
+                WebhookEvent::PaymentCardToken(TokenEvent {
+                    order_id: data.order_id,
+                    credit_card_token,
+                    card_masked_number: attributes.card_masked_number,
+                    card_brand: attributes.card_brand,
+                    card_expiration: attributes.card_expiration,
+                    card_holder_name: attributes.card_holder_name,
+                    meta,
+                })
+            }
+            WebhookType::Unknown(webhook_type) => WebhookEvent::Unknown(UnknownEvent {
+                webhook_type,
+                order_id: data.order_id,
+                psp_name: data.psp_name,
+                charge: data.charge,
+                meta,
+            }),
+        };
+
+        Ok(event)
+    }
+}
+
+impl<'de> Deserialize<'de> for WebhookEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let payload = WebhookPayload::deserialize(deserializer)?;
+
+        WebhookEvent::try_from(payload).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookEvent;
+    use crate::webhook::test_support::webhook_json;
+    use crate::webhook::{ChargeAttributesStatus, WebhookType};
+
+    fn body(webhook_type: &str, charge: serde_json::Value) -> Vec<u8> {
+        webhook_json(webhook_type, charge).to_string().into_bytes()
+    }
+
+    #[test]
+    fn parses_event_of_webhook_type() {
+        let charge = serde_json::json!({
+            "type": "charge",
+            "attributes": { "status": "approved", "credit_card_token": "token-1", "created_at": 1 },
+        });
+
+        let received = WebhookEvent::parse(&body("approved", charge.clone())).unwrap();
+        let WebhookEvent::Approved(event) = &received.event else {
+            panic!("unexpected {:?}", received.event);
+        };
+        assert_eq!(event.charge.attributes.status, ChargeAttributesStatus::Approved);
+        assert!(received.raw.contains("\"approved\""));
+
+        let received = WebhookEvent::parse(&body("payment_card_token", charge)).unwrap();
+        let WebhookEvent::PaymentCardToken(event) = &received.event else {
+            panic!("unexpected {:?}", received.event);
+        };
+        assert_eq!(event.credit_card_token, "token-1");
+
+        let received = WebhookEvent::parse(&body("cashier.session.init", serde_json::Value::Null));
+        assert_eq!(received.unwrap().event.get_type(), WebhookType::CashierSessionInit);
+
+        assert!(WebhookEvent::parse(&body("declined", serde_json::Value::Null)).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::webhook::dedup::{get_dedup_key, DedupState, WebhookDedupStore};
+use crate::webhook::event::{ChargeEvent, SessionEvent, TokenEvent, UnknownEvent, WebhookEvent};
+use crate::webhook::verify::{WebhookVerifier, WebhookVerifyError};
+use http::{HeaderMap, StatusCode};
+
+pub type WebhookHandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Application side of the webhook endpoint. Every method is called with a verified event of
+/// the matching `webhook.type`. An error makes the endpoint respond with 500 so BridgerPay
+/// sends the webhook again later.
+#[async_trait::async_trait]
+pub trait WebhookHandler: Send + Sync {
+    async fn on_approved(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_declined(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_approved_on_hold(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_authorized(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_voided(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_cashier_session_init(
+        &self,
+        _event: SessionEvent,
+    ) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_cashier_session_close(
+        &self,
+        _event: SessionEvent,
+    ) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    async fn on_payment_card_token(&self, _event: TokenEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+
+    /// Called for webhook types this version of the crate doesn't know. Acknowledged by default
+    /// so BridgerPay doesn't retry them forever.
+    async fn on_unknown(&self, _event: UnknownEvent) -> Result<(), WebhookHandlerError> {
+        Ok(())
+    }
+}
+
+/// Verifies, parses and dispatches raw webhook requests to a [`WebhookHandler`]. Framework
+/// independent, see `receiver` for the axum router.
+pub struct WebhookDispatcher<H: WebhookHandler> {
+    verifier: WebhookVerifier,
+    handler: H,
+    lenient: bool,
+    dedup_store: Option<Box<dyn WebhookDedupStore>>,
+}
+
+impl<H: WebhookHandler> WebhookDispatcher<H> {
+    pub fn new(verifier: WebhookVerifier, handler: H) -> Self {
+        Self {
+            verifier,
+            handler,
+            lenient: false,
+            dedup_store: None,
+        }
+    }
+
+    /// Handles every webhook once, duplicates are acknowledged with 200 without calling the
+    /// handler. Duplicates of a webhook still being handled are answered with 409, so BridgerPay
+    /// retries them in case the handler fails. Webhooks the handler failed on are handled again
+    /// when retried.
+    pub fn with_dedup_store(mut self, dedup_store: impl WebhookDedupStore + 'static) -> Self {
+        self.dedup_store = Some(Box::new(dedup_store));
+        self
+    }
+
+    /// Parses with [`WebhookEvent::parse_lenient`], so webhooks with invalid informational fields
+    /// are handled instead of being rejected with 400.
+    pub fn with_lenient_parsing(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    pub fn get_handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Status code to respond with: 401 for an invalid signature or time, 400 for a body that
+    /// can't be parsed, 409 for a duplicate still being handled, 500 when the handler fails and
+    /// 200 otherwise.
+    pub async fn dispatch(&self, raw_body: &[u8], headers: &HeaderMap) -> StatusCode {
+        match self.verifier.verify(raw_body, headers) {
+            Ok(()) => {}
+            Err(WebhookVerifyError::MalformedPayload(_)) => return StatusCode::BAD_REQUEST,
+            Err(_) => return StatusCode::UNAUTHORIZED,
+        }
+
+        let event = if self.lenient {
+            WebhookEvent::parse_lenient(raw_body).map(|(received, report)| {
+                if !report.is_clean() && std::env::var("DEBUG").is_ok() {
+                    println!("webhook parsed leniently: {:?}", report);
+                }
+
+                received.event
+            })
+        } else {
+            serde_json::from_slice::<WebhookEvent>(raw_body)
+        };
+
+        let Ok(event) = event else {
+            return StatusCode::BAD_REQUEST;
+        };
+
+        let dedup_key = get_dedup_key(&event);
+
+        if let Some(dedup_store) = &self.dedup_store {
+            match dedup_store.try_start(&dedup_key).await {
+                Ok(DedupState::New) => {}
+                Ok(DedupState::InProgress) => return StatusCode::CONFLICT,
+                Ok(DedupState::Done) => return StatusCode::OK,
+                Err(err) => {
+                    if std::env::var("DEBUG").is_ok() {
+                        println!("webhook dedup store failed: {}", err);
+                    }
+
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+            }
+        }
+
+        let result = match event {
+            WebhookEvent::Approved(event) => self.handler.on_approved(event).await,
+            WebhookEvent::Declined(event) => self.handler.on_declined(event).await,
+            WebhookEvent::ApprovedOnHold(event) => self.handler.on_approved_on_hold(event).await,
+            WebhookEvent::Authorized(event) => self.handler.on_authorized(event).await,
+            WebhookEvent::Voided(event) => self.handler.on_voided(event).await,
+            WebhookEvent::CashierSessionInit(event) => {
+                self.handler.on_cashier_session_init(event).await
+            }
+            WebhookEvent::CashierSessionClosed(event) => {
+                self.handler.on_cashier_session_close(event).await
+            }
+            WebhookEvent::PaymentCardToken(event) => {
+                self.handler.on_payment_card_token(event).await
+            }
+            WebhookEvent::Unknown(event) => self.handler.on_unknown(event).await,
+        };
+
+        match result {
+            Ok(()) => {
+                if let Some(dedup_store) = &self.dedup_store {
+                    // the webhook is handled, asking for a retry would handle it twice
+                    if let Err(err) = dedup_store.finish(&dedup_key).await {
+                        if std::env::var("DEBUG").is_ok() {
+                            println!("webhook dedup store failed: {}", err);
+                        }
+                    }
+                }
+
+                StatusCode::OK
+            }
+            Err(err) => {
+                if std::env::var("DEBUG").is_ok() {
+                    println!("webhook handler failed: {}", err);
+                }
+
+                if let Some(dedup_store) = &self.dedup_store {
+                    let _ = dedup_store.unmark(&dedup_key).await;
+                }
+
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookDispatcher;
+    use crate::webhook::dedup::InMemoryDedupStore;
+    use crate::webhook::test_support::{body, FailingDeclines};
+    use crate::webhook::verify::{WebhookVerifier, SIGNATURE_HEADER};
+    use http::{HeaderMap, StatusCode};
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    fn signed(body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let signature = WebhookVerifier::new("secret").sign(body);
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn responds_with_status_for_retries() {
+        let verifier = WebhookVerifier::new("secret");
+        let dispatcher = WebhookDispatcher::new(verifier, FailingDeclines::default());
+        let approved = body("approved");
+        let declined = body("declined");
+        let invalid = b"{\"meta\": 1}".to_vec();
+
+        assert_eq!(dispatcher.dispatch(&approved, &signed(&approved)).await, StatusCode::OK);
+        assert_eq!(
+            dispatcher.dispatch(&declined, &signed(&declined)).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            dispatcher.dispatch(&approved, &signed(&declined)).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            dispatcher.dispatch(&invalid, &signed(&invalid)).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn handles_duplicates_once() {
+        let verifier = WebhookVerifier::new("secret");
+        let dispatcher = WebhookDispatcher::new(verifier, FailingDeclines::default())
+            .with_dedup_store(InMemoryDedupStore::new(Duration::from_secs(60)));
+        let approved = body("approved");
+        let declined = body("declined");
+
+        for _ in 0..2 {
+            let status = dispatcher.dispatch(&approved, &signed(&approved)).await;
+            assert_eq!(status, StatusCode::OK);
+            let status = dispatcher.dispatch(&declined, &signed(&declined)).await;
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let handler = dispatcher.get_handler();
+        assert_eq!(handler.approved.load(Ordering::SeqCst), 1);
+        assert_eq!(handler.declined.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_duplicates_in_progress() {
+        let verifier = WebhookVerifier::new("secret");
+        let handler = FailingDeclines {
+            delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let dispatcher = WebhookDispatcher::new(verifier, handler)
+            .with_dedup_store(InMemoryDedupStore::new(Duration::from_secs(60)));
+        let declined = body("declined");
+        let headers = signed(&declined);
+
+        let (first, second) = tokio::join!(dispatcher.dispatch(&declined, &headers), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            dispatcher.dispatch(&declined, &headers).await
+        });
+
+        assert_eq!(first, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(second, StatusCode::CONFLICT);
+        assert_eq!(dispatcher.get_handler().declined.load(Ordering::SeqCst), 1);
+
+        let status = dispatcher.dispatch(&declined, &headers).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(dispatcher.get_handler().declined.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,224 @@
+//! Lenient webhook parsing that survives API changes.
+//!
+//! An optional field of an unexpected type is parsed as `None` instead of failing the whole
+//! webhook, but only if it is in [`DROPPABLE_FIELDS`]. Required fields and the ones payments
+//! depend on, e.g. `data.charge.id` or `data.charge.attributes.amount`, still have to be valid.
+
+use crate::webhook::event::{ReceivedWebhook, WebhookEvent};
+use crate::webhook::{ChargeAttributesStatus, ChargeOperationType, WebhookType};
+use serde_json::Value;
+use serde_path_to_error::{Path, Segment};
+
+/// Stops parsing payloads that are mostly invalid.
+const MAX_DROPPED_FIELDS: usize = 16;
+
+/// Informational fields that are parsed as `None` when invalid, with everything nested in them.
+pub const DROPPABLE_FIELDS: [&str; 31] = [
+    "data.psp_name",
+    "data.charge.psp_order_id",
+    "data.charge.is_refundable",
+    "data.charge.refund_id",
+    "data.charge.deposit_source",
+    "data.charge.is_recurring",
+    "data.charge.mid_type",
+    "data.charge.cft_id",
+    "data.charge.attributes.is3_d",
+    "data.charge.attributes.live_mode",
+    "data.charge.attributes.card_number",
+    "data.charge.attributes.payment_method",
+    "data.charge.attributes.description",
+    "data.charge.attributes.decline_code",
+    "data.charge.attributes.decline_reason",
+    "data.charge.attributes.reference_id",
+    "data.charge.attributes.pos_terminal_id",
+    "data.charge.attributes.cash_register_id",
+    "data.charge.attributes.source",
+    "data.charge.attributes.card_masked_number",
+    "data.charge.attributes.card_expiration",
+    "data.charge.attributes.card_brand",
+    "data.charge.attributes.card_holder_name",
+    "data.charge.attributes.customer",
+    "data.charge.attributes.mid_alias",
+    "data.charge.attributes.is_declined_due_to_funds",
+    "data.charge.attributes.is_hard_decline",
+    "data.charge.attributes.wire_transfer_details",
+    "data.charge.attributes.verifications",
+    "data.charge.attributes.crypto_currency",
+    "meta.tracking_id",
+];
+
+/// Parts of the webhook this version of the crate doesn't understand.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseReport {
+    /// Fields missing in the models, e.g. added to the API later.
+    pub ignored_fields: Vec<String>,
+    /// Fields of [`DROPPABLE_FIELDS`] of an unexpected type, parsed as `None`, and the error.
+    pub dropped_fields: Vec<(String, String)>,
+    /// Fields parsed into an `Unknown` enum variant.
+    pub unknown_values: Vec<String>,
+}
+
+impl ParseReport {
+    pub fn is_clean(&self) -> bool {
+        self.ignored_fields.is_empty()
+            && self.dropped_fields.is_empty()
+            && self.unknown_values.is_empty()
+    }
+}
+
+impl WebhookEvent {
+    /// Same as [`WebhookEvent::parse`] but tolerates invalid optional fields and reports
+    /// everything that was not understood.
+    pub fn parse_lenient(
+        raw_body: &[u8],
+    ) -> Result<(ReceivedWebhook, ParseReport), serde_json::Error> {
+        let mut value: Value = serde_json::from_slice(raw_body)?;
+        let mut report = ParseReport::default();
+
+        loop {
+            let mut ignored_fields = Vec::new();
+            let mut on_ignored = |path: serde_ignored::Path| ignored_fields.push(format_path(&path));
+            let deserializer = serde_ignored::Deserializer::new(&value, &mut on_ignored);
+            let result: Result<WebhookEvent, _> = serde_path_to_error::deserialize(deserializer);
+
+            let error = match result {
+                Ok(event) => {
+                    report.ignored_fields = ignored_fields;
+                    report.unknown_values = get_unknown_values(&event);
+                    let raw = String::from_utf8_lossy(raw_body).into_owned();
+
+                    return Ok((ReceivedWebhook { event, raw }, report));
+                }
+                Err(error) => error,
+            };
+
+            let path = error.path().to_string();
+            let is_dropped = report.dropped_fields.iter().any(|(dropped, _)| *dropped == path);
+
+            // a field that fails as null too is required
+            if is_dropped
+                || !is_droppable(&path)
+                || report.dropped_fields.len() >= MAX_DROPPED_FIELDS
+                || !set_null(&mut value, error.path())
+            {
+                return Err(error.into_inner());
+            }
+
+            report.dropped_fields.push((path, error.inner().to_string()));
+        }
+    }
+}
+
+fn is_droppable(path: &str) -> bool {
+    DROPPABLE_FIELDS.iter().any(|field| {
+        path.strip_prefix(field)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+/// Dotted path like the one of `serde_path_to_error`, without the `?` of `Option` fields.
+fn format_path(path: &serde_ignored::Path) -> String {
+    let (parent, segment) = match path {
+        serde_ignored::Path::Root => return String::new(),
+        serde_ignored::Path::Seq { parent, index } => (parent, index.to_string()),
+        serde_ignored::Path::Map { parent, key } => (parent, key.clone()),
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => return format_path(parent),
+    };
+
+    match format_path(parent) {
+        parent if parent.is_empty() => segment,
+        parent => format!("{parent}.{segment}"),
+    }
+}
+
+fn set_null(value: &mut Value, path: &Path) -> bool {
+    let mut target = value;
+
+    for segment in path.iter() {
+        let next = match segment {
+            Segment::Seq { index } => target.get_mut(*index),
+            Segment::Map { key } => target.get_mut(key.as_str()),
+            Segment::Enum { .. } => continue,
+            Segment::Unknown => None,
+        };
+
+        match next {
+            Some(next) => target = next,
+            None => return false,
+        }
+    }
+
+    if target.is_null() {
+        return false;
+    }
+
+    *target = Value::Null;
+
+    true
+}
+
+fn get_unknown_values(event: &WebhookEvent) -> Vec<String> {
+    let mut unknown_values = Vec::new();
+
+    if let WebhookType::Unknown(value) = event.get_type() {
+        unknown_values.push(format!("webhook.type = {value}"));
+    }
+
+    if let Some(charge) = event.get_charge() {
+        if let ChargeAttributesStatus::Unknown(value) = &charge.attributes.status {
+            unknown_values.push(format!("data.charge.attributes.status = {value}"));
+        }
+
+        if let Some(ChargeOperationType::Unknown(value)) = &charge.operation_type {
+            unknown_values.push(format!("data.charge.operation_type = {value}"));
+        }
+    }
+
+    unknown_values
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::webhook::event::WebhookEvent;
+    use crate::webhook::test_support::webhook_json;
+    use crate::webhook::ChargeAttributesStatus;
+
+    #[test]
+    fn drops_invalid_optional_fields_and_reports_unknowns() {
+        let charge = serde_json::json!({
+            "type": "charge",
+            "is_refundable": "yes",
+            "attributes": { "status": "disputed", "card_brand": 5, "created_at": 1 },
+            "dispute_id": "dispute-1",
+        });
+        let body = webhook_json("chargeback", charge).to_string();
+
+        assert!(WebhookEvent::parse(body.as_bytes()).is_err());
+
+        let (received, report) = WebhookEvent::parse_lenient(body.as_bytes()).unwrap();
+        let WebhookEvent::Unknown(event) = received.event else {
+            panic!("unexpected {:?}", received.event);
+        };
+        let charge = event.charge.unwrap();
+        assert_eq!(
+            charge.attributes.status,
+            ChargeAttributesStatus::Unknown("disputed".to_string())
+        );
+        assert!(charge.attributes.card_brand.is_none());
+        assert_eq!(report.ignored_fields, ["data.charge.dispute_id"]);
+        let dropped: Vec<_> = report.dropped_fields.iter().map(|(path, _)| path).collect();
+        assert_eq!(dropped, ["data.charge.attributes.card_brand", "data.charge.is_refundable"]);
+        assert_eq!(report.unknown_values.len(), 2);
+
+        let invalid = body.replace("\"created_at\":1", "\"created_at\":\"now\"");
+        assert!(WebhookEvent::parse_lenient(invalid.as_bytes()).is_err());
+
+        // payments depend on the amount and the charge id, so they are never dropped
+        let invalid = body.replace("\"card_brand\":5", "\"amount\":\"ten\"");
+        assert!(WebhookEvent::parse_lenient(invalid.as_bytes()).is_err());
+        let invalid = body.replace("\"is_refundable\"", "\"id\":5,\"is_refundable\"");
+        assert!(WebhookEvent::parse_lenient(invalid.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Payment lifecycle of an order built from webhooks.
+//!
+//! Webhooks can arrive out of order, so status changes of every charge are ordered by
+//! `updated_at`, or `created_at` when not set, and the transitions are checked in that order.
+
+use crate::money::Amount;
+use crate::webhook::{Charge, ChargeAttributesStatus, ChargeOperationType, WebhookPayload};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub status: ChargeAttributesStatus,
+    /// Unix time of the change.
+    pub time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionViolation {
+    pub charge_id: String,
+    pub from: ChargeAttributesStatus,
+    pub to: ChargeAttributesStatus,
+    pub time: u64,
+}
+
+impl fmt::Display for TransitionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Charge {} can't change from {} to {} at {}",
+            self.charge_id, self.from, self.to, self.time
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleError {
+    /// The webhook belongs to another order.
+    OtherOrder { expected: String, actual: String },
+    /// The charge has neither `id` nor `uuid`.
+    MissingChargeId,
+    /// The change was recorded but breaks the lifecycle of the charge.
+    IllegalTransition(TransitionViolation),
+}
+
+impl fmt::Display for LifecycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LifecycleError::OtherOrder { expected, actual } => {
+                write!(f, "Webhook of order {actual} applied to order {expected}")
+            }
+            LifecycleError::MissingChargeId => write!(f, "Charge has no id"),
+            LifecycleError::IllegalTransition(violation) => violation.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+/// Summary of all charges of an order.
+#[derive(strum::Display, Debug, Clone, PartialEq)]
+pub enum OrderState {
+    /// No charge with a known outcome yet.
+    Pending,
+    /// A deposit is authorized and waits for capture or void.
+    Authorized,
+    /// A deposit is approved on hold and waits for review.
+    OnHold,
+    Paid,
+    PartiallyRefunded,
+    Refunded,
+    /// Every deposit is declined or voided.
+    Failed,
+}
+
+impl OrderState {
+    /// Whether the state changes only with a new charge, e.g. a refund.
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Paid
+                | OrderState::PartiallyRefunded
+                | OrderState::Refunded
+                | OrderState::Failed
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChargeLifecycle {
+    pub charge_id: String,
+    pub operation_type: Option<ChargeOperationType>,
+    pub amount: Option<Amount>,
+    /// Ordered by time, never empty.
+    history: Vec<StatusChange>,
+}
+
+impl ChargeLifecycle {
+    pub fn get_status(&self) -> &ChargeAttributesStatus {
+        &self.history[self.history.len() - 1].status
+    }
+
+    pub fn get_history(&self) -> &[StatusChange] {
+        &self.history
+    }
+
+    pub fn get_violations(&self) -> Vec<TransitionViolation> {
+        self.history
+            .windows(2)
+            .filter(|pair| !is_legal_transition(&pair[0].status, &pair[1].status))
+            .map(|pair| TransitionViolation {
+                charge_id: self.charge_id.clone(),
+                from: pair[0].status.clone(),
+                to: pair[1].status.clone(),
+                time: pair[1].time,
+            })
+            .collect()
+    }
+
+    fn record(&mut self, change: StatusChange) {
+        if self.history.contains(&change) {
+            return;
+        }
+
+        // changes at the same second are ordered by their usual order in the lifecycle
+        let key = |change: &StatusChange| (change.time, get_status_rank(&change.status));
+        let index = self
+            .history
+            .partition_point(|recorded| key(recorded) <= key(&change));
+        self.history.insert(index, change);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderLifecycle {
+    order_id: String,
+    charges: Vec<ChargeLifecycle>,
+}
+
+impl OrderLifecycle {
+    pub fn new(order_id: impl Into<String>) -> Self {
+        Self {
+            order_id: order_id.into(),
+            charges: Vec::new(),
+        }
+    }
+
+    pub fn get_order_id(&self) -> &str {
+        &self.order_id
+    }
+
+    pub fn get_charges(&self) -> &[ChargeLifecycle] {
+        &self.charges
+    }
+
+    pub fn get_charge(&self, charge_id: &str) -> Option<&ChargeLifecycle> {
+        self.charges
+            .iter()
+            .find(|charge| charge.charge_id == charge_id)
+    }
+
+    /// Records the charge status of the webhook. Webhooks without a charge, e.g. cashier
+    /// session ones, are ignored.
+    pub fn apply(&mut self, payload: &WebhookPayload) -> Result<(), LifecycleError> {
+        if payload.data.order_id != self.order_id {
+            return Err(LifecycleError::OtherOrder {
+                expected: self.order_id.clone(),
+                actual: payload.data.order_id.clone(),
+            });
+        }
+
+        match &payload.data.charge {
+            Some(charge) => self.apply_charge(charge),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the charge status, e.g. of a charge returned by `RestApiClient`. Illegal
+    /// transitions are recorded too and stay in [`Self::get_violations`].
+    pub fn apply_charge(&mut self, charge: &Charge) -> Result<(), LifecycleError> {
+        let charge_id = charge
+            .id
+            .as_ref()
+            .or(charge.uuid.as_ref())
+            .ok_or(LifecycleError::MissingChargeId)?;
+        let attributes = &charge.attributes;
+        let change = StatusChange {
+            status: attributes.status.clone(),
+            time: attributes.updated_at.unwrap_or(attributes.created_at),
+        };
+
+        let index = match self
+            .charges
+            .iter()
+            .position(|lifecycle| lifecycle.charge_id == *charge_id)
+        {
+            Some(index) => index,
+            None => {
+                self.charges.push(ChargeLifecycle {
+                    charge_id: charge_id.clone(),
+                    operation_type: charge.operation_type.clone(),
+                    amount: attributes.amount,
+                    history: Vec::new(),
+                });
+                self.charges.len() - 1
+            }
+        };
+
+        let lifecycle = &mut self.charges[index];
+        let violations = lifecycle.get_violations();
+        lifecycle.record(change);
+
+        lifecycle.operation_type = lifecycle
+            .operation_type
+            .take()
+            .or(charge.operation_type.clone());
+        lifecycle.amount = lifecycle.amount.or(attributes.amount);
+
+        let new_violation = lifecycle
+            .get_violations()
+            .into_iter()
+            .find(|violation| !violations.contains(violation));
+
+        match new_violation {
+            Some(violation) => Err(LifecycleError::IllegalTransition(violation)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_violations(&self) -> Vec<TransitionViolation> {
+        self.charges
+            .iter()
+            .flat_map(|charge| charge.get_violations())
+            .collect()
+    }
+
+    pub fn get_state(&self) -> OrderState {
+        let mut paid = Amount::ZERO;
+        let mut refunded = Amount::ZERO;
+        let mut deposit_statuses = Vec::new();
+
+        for charge in &self.charges {
+            let status = charge.get_status();
+            let amount = charge.amount.unwrap_or(Amount::ZERO);
+
+            match charge.operation_type {
+                Some(ChargeOperationType::Refund) => {
+                    if *status == ChargeAttributesStatus::Approved {
+                        refunded = refunded.checked_add(amount).unwrap_or(refunded);
+                    }
+                }
+                Some(ChargeOperationType::Payout) => {}
+                _ => {
+                    if *status == ChargeAttributesStatus::Approved {
+                        paid = paid.checked_add(amount).unwrap_or(paid);
+                    }
+
+                    deposit_statuses.push(status);
+                }
+            }
+        }
+
+        let has_status = |expected: ChargeAttributesStatus| {
+            deposit_statuses.iter().any(|status| **status == expected)
+        };
+
+        if has_status(ChargeAttributesStatus::Approved) {
+            if refunded.is_zero() {
+                OrderState::Paid
+            } else if refunded >= paid {
+                OrderState::Refunded
+            } else {
+                OrderState::PartiallyRefunded
+            }
+        } else if has_status(ChargeAttributesStatus::Authorized) {
+            OrderState::Authorized
+        } else if has_status(ChargeAttributesStatus::ApprovedOnHold) {
+            OrderState::OnHold
+        } else if !deposit_statuses.is_empty()
+            && deposit_statuses.iter().all(|status| {
+                matches!(
+                    status,
+                    ChargeAttributesStatus::Declined | ChargeAttributesStatus::Voided
+                )
+            })
+        {
+            OrderState::Failed
+        } else {
+            OrderState::Pending
+        }
+    }
+}
+
+fn is_legal_transition(from: &ChargeAttributesStatus, to: &ChargeAttributesStatus) -> bool {
+    use ChargeAttributesStatus::*;
+
+    match (from, to) {
+        (from, to) if from == to => true,
+        // statuses added later can't be checked
+        (Unknown(_), _) | (_, Unknown(_)) => true,
+        (Authorized, Approved | ApprovedOnHold | Declined | Voided) => true,
+        (ApprovedOnHold, Approved | Declined) => true,
+        _ => false,
+    }
+}
+
+fn get_status_rank(status: &ChargeAttributesStatus) -> u8 {
+    match status {
+        ChargeAttributesStatus::Authorized => 0,
+        ChargeAttributesStatus::ApprovedOnHold => 1,
+        ChargeAttributesStatus::Unknown(_) => 2,
+        ChargeAttributesStatus::Approved
+        | ChargeAttributesStatus::Declined
+        | ChargeAttributesStatus::Voided => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LifecycleError, OrderLifecycle, OrderState};
+    use crate::webhook::test_support::webhook_json;
+    use crate::webhook::{ChargeAttributesStatus, WebhookPayload};
+
+    fn payload(charge_id: &str, operation_type: &str, status: &str, time: u64) -> WebhookPayload {
+        let charge = serde_json::json!({
+            "type": "charge",
+            "id": charge_id,
+            "operation_type": operation_type,
+            "attributes": {
+                "status": status,
+                "amount": if operation_type == "refund" { 30 } else { 100 },
+                "created_at": 1,
+                "updated_at": time,
+            },
+        });
+
+        serde_json::from_value(webhook_json(status, charge)).unwrap()
+    }
+
+    #[test]
+    fn orders_webhooks_and_flags_illegal_transitions() {
+        let mut order = OrderLifecycle::new("order-1");
+
+        order.apply(&payload("charge-1", "deposit", "approved", 20)).unwrap();
+        order.apply(&payload("charge-1", "deposit", "authorized", 10)).unwrap();
+        order.apply(&payload("charge-1", "deposit", "approved", 20)).unwrap();
+
+        let charge = order.get_charge("charge-1").unwrap();
+        assert_eq!(charge.get_status(), &ChargeAttributesStatus::Approved);
+        assert_eq!(charge.get_history().len(), 2);
+        assert_eq!(order.get_state(), OrderState::Paid);
+
+        order.apply(&payload("charge-2", "refund", "approved", 30)).unwrap();
+        assert_eq!(order.get_state(), OrderState::PartiallyRefunded);
+        assert!(order.get_state().is_settled());
+
+        let error = order
+            .apply(&payload("charge-1", "deposit", "voided", 40))
+            .unwrap_err();
+        let LifecycleError::IllegalTransition(violation) = error else {
+            panic!("unexpected {error:?}");
+        };
+        assert_eq!(violation.from, ChargeAttributesStatus::Approved);
+        assert_eq!(order.get_violations(), [violation]);
+    }
+}
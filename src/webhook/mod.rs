@@ -0,0 +1,13 @@
+pub mod dedup;
+pub mod event;
+pub mod handler;
+pub mod lenient;
+pub mod lifecycle;
+pub mod models;
+#[cfg(feature = "webhook-receiver")]
+pub mod receiver;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod verify;
+
+pub use models::*;
@@ -1,3 +1,4 @@
+use crate::money::Amount;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,32 +24,43 @@ pub struct Webhook {
     pub webhook_type: WebhookType,
 }
 
-#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+#[derive(
+    strum::Display, strum::EnumString, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq,
+)]
+#[serde(from = "String", into = "String")]
 pub enum WebhookType {
     #[strum(to_string = "approved")]
-    #[serde(rename = "approved")]
     Approved,
     #[strum(to_string = "declined")]
-    #[serde(rename = "declined")]
     Declined,
     #[strum(to_string = "approved_on_hold")]
-    #[serde(rename = "approved_on_hold")]
     ApprovedOnHold,
     #[strum(to_string = "authorized")]
-    #[serde(rename = "authorized")]
     Authorized,
     #[strum(to_string = "voided")]
-    #[serde(rename = "voided")]
     Voided,
     #[strum(to_string = "cashier.session.init")]
-    #[serde(rename = "cashier.session.init")]
     CashierSessionInit,
     #[strum(to_string = "cashier.session.close")]
-    #[serde(rename = "cashier.session.close")]
     CashierSessionClosed,
     #[strum(to_string = "payment_card_token")]
-    #[serde(rename = "payment_card_token")]
     PaymentCardToken,
+    /// Value added to the API after this version of the crate.
+    #[strum(default)]
+    Unknown(String),
+}
+
+impl From<String> for WebhookType {
+    fn from(value: String) -> Self {
+        // never fails because of the `Unknown` variant
+        value.parse().unwrap()
+    }
+}
+
+impl From<WebhookType> for String {
+    fn from(value: WebhookType) -> Self {
+        value.to_string()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,7 +70,7 @@ pub struct WebhookData {
     pub charge: Option<Charge>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Charge {
     #[serde(rename = "type")]
     pub charge_type: String,
@@ -76,24 +88,40 @@ pub struct Charge {
     pub order_id: Option<String>,
 }
 
-#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+#[derive(
+    strum::Display, strum::EnumString, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq,
+)]
+#[serde(from = "String", into = "String")]
 pub enum ChargeOperationType {
     #[strum(to_string = "deposit")]
-    #[serde(rename = "deposit")]
     Deposit,
     #[strum(to_string = "refund")]
-    #[serde(rename = "refund")]
     Refund,
     #[strum(to_string = "payout")]
-    #[serde(rename = "payout")]
     Payout,
+    /// Value added to the API after this version of the crate.
+    #[strum(default)]
+    Unknown(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<String> for ChargeOperationType {
+    fn from(value: String) -> Self {
+        // never fails because of the `Unknown` variant
+        value.parse().unwrap()
+    }
+}
+
+impl From<ChargeOperationType> for String {
+    fn from(value: ChargeOperationType) -> Self {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChargeAttributes {
     pub is3_d: Option<bool>,
     pub live_mode: Option<bool>,
-    pub amount: Option<f64>,
+    pub amount: Option<Amount>,
     pub status: ChargeAttributesStatus,
     pub card_number: Option<String>,
     pub currency: Option<String>,
@@ -122,33 +150,47 @@ pub struct ChargeAttributes {
     pub crypto_currency: Option<String>,
 }
 
-#[derive(strum::Display, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq)]
+#[derive(
+    strum::Display, strum::EnumString, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq,
+)]
+#[serde(from = "String", into = "String")]
 pub enum ChargeAttributesStatus {
     #[strum(to_string = "approved")]
-    #[serde(rename = "approved")]
     Approved,
     #[strum(to_string = "approved_on_hold")]
-    #[serde(rename = "approved_on_hold")]
     ApprovedOnHold,
     #[strum(to_string = "declined")]
-    #[serde(rename = "declined")]
     Declined,
     #[strum(to_string = "authorized")]
-    #[serde(rename = "authorized")]
     Authorized,
     #[strum(to_string = "voided")]
-    #[serde(rename = "voided")]
     Voided,
+    /// Value added to the API after this version of the crate.
+    #[strum(default)]
+    Unknown(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<String> for ChargeAttributesStatus {
+    fn from(value: String) -> Self {
+        // never fails because of the `Unknown` variant
+        value.parse().unwrap()
+    }
+}
+
+impl From<ChargeAttributesStatus> for String {
+    fn from(value: ChargeAttributesStatus) -> Self {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttributesSource {
     pub email: Option<String>,
     pub ip_address: Option<String>,
     pub name: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttributesCustomer {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
@@ -160,14 +202,14 @@ pub struct AttributesCustomer {
     pub extra_data: serde_json::Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttributesVerifications {
     pub cavv: Option<String>,
     pub cavv_message: Option<String>,
     pub avs: Option<Avs>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Avs {
     pub result: Option<String>,
     pub zip_match: Option<String>,
@@ -176,7 +218,7 @@ pub struct Avs {
     pub message: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookMeta {
     pub server_time: u64,
     pub server_timezone: String,
@@ -194,6 +236,18 @@ mod tests {
 
     #[test]
     pub fn test() {
-        println!("{}", WebhookType::CashierSessionClosed.to_string());
+        println!("{}", WebhookType::CashierSessionClosed);
+    }
+
+    #[test]
+    fn keeps_unknown_values() {
+        let webhook_type: WebhookType = serde_json::from_str("\"refunded\"").unwrap();
+
+        assert_eq!(webhook_type, WebhookType::Unknown("refunded".to_string()));
+        assert_eq!(serde_json::to_string(&webhook_type).unwrap(), "\"refunded\"");
+        assert_eq!(
+            serde_json::from_str::<WebhookType>("\"cashier.session.init\"").unwrap(),
+            WebhookType::CashierSessionInit
+        );
     }
 }
@@ -0,0 +1,71 @@
+//! Ready to mount axum endpoint for BridgerPay webhooks.
+
+use crate::webhook::handler::{WebhookDispatcher, WebhookHandler};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use std::sync::Arc;
+
+/// Router answering `POST path` with the status from [`WebhookDispatcher::dispatch`].
+/// Merge it into the application router, e.g. `app.merge(webhook_router("/webhooks", dispatcher))`.
+pub fn webhook_router<H: WebhookHandler + 'static>(
+    path: &str,
+    dispatcher: WebhookDispatcher<H>,
+) -> Router {
+    Router::new()
+        .route(path, post(receive_webhook::<H>))
+        .with_state(Arc::new(dispatcher))
+}
+
+async fn receive_webhook<H: WebhookHandler + 'static>(
+    State(dispatcher): State<Arc<WebhookDispatcher<H>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    dispatcher.dispatch(&body, &headers).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::webhook_router;
+    use crate::webhook::handler::WebhookDispatcher;
+    use crate::webhook::test_support::{body, FailingDeclines};
+    use crate::webhook::verify::{WebhookVerifier, SIGNATURE_HEADER};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    async fn post(body: Vec<u8>, signature: &str) -> StatusCode {
+        let handler = FailingDeclines::default();
+        let dispatcher = WebhookDispatcher::new(WebhookVerifier::new("secret"), handler);
+        let request = Request::post("/webhooks")
+            .header(SIGNATURE_HEADER, signature)
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = webhook_router("/webhooks", dispatcher)
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn responds_with_dispatcher_status() {
+        let verifier = WebhookVerifier::new("secret");
+        let approved = body("approved");
+        let declined = body("declined");
+        let oversized = vec![b' '; 3 * 1024 * 1024];
+
+        let signature = verifier.sign(&approved);
+        assert_eq!(post(approved.clone(), &signature).await, StatusCode::OK);
+        let signature = verifier.sign(&declined);
+        assert_eq!(post(approved, &signature).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(post(declined, &signature).await, StatusCode::INTERNAL_SERVER_ERROR);
+        let signature = verifier.sign(&oversized);
+        assert_eq!(post(oversized, &signature).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,55 @@
+//! Webhook fixtures shared by the tests of the crate.
+
+use crate::webhook::event::ChargeEvent;
+use crate::webhook::handler::{WebhookHandler, WebhookHandlerError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Webhook of order `order-1` with the charge, `Value::Null` for webhooks without one.
+pub fn webhook_json(webhook_type: &str, charge: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "webhook": { "type": webhook_type },
+        "data": { "order_id": "order-1", "charge": charge },
+        "meta": {
+            "server_time": 1,
+            "server_timezone": "UTC",
+            "api_version": "v2",
+            "cashier_session_id": "session-1",
+        },
+    })
+}
+
+/// Body of a webhook sent now, with a charge of the same status as the webhook type.
+pub fn body(webhook_type: &str) -> Vec<u8> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let charge = serde_json::json!({
+        "type": "charge",
+        "attributes": { "status": webhook_type, "created_at": 1 },
+    });
+    let mut webhook = webhook_json(webhook_type, charge);
+    webhook["meta"]["server_time"] = now.as_secs().into();
+
+    webhook.to_string().into_bytes()
+}
+
+/// Handler counting approved and declined webhooks, it fails on declined ones after the delay.
+#[derive(Default)]
+pub struct FailingDeclines {
+    pub approved: AtomicUsize,
+    pub declined: AtomicUsize,
+    pub delay: Duration,
+}
+
+#[async_trait::async_trait]
+impl WebhookHandler for FailingDeclines {
+    async fn on_approved(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        self.approved.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn on_declined(&self, _event: ChargeEvent) -> Result<(), WebhookHandlerError> {
+        self.declined.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        Err("database is down".into())
+    }
+}
@@ -0,0 +1,160 @@
+//! Verification of the webhook HMAC signature.
+//!
+//! BridgerPay signs the raw request body with HMAC-SHA256 using the webhook secret and sends the
+//! base64 encoded signature in the [`SIGNATURE_HEADER`] header.
+
+use crate::webhook::WebhookMetaValue;
+use base64::engine::general_purpose;
+use base64::Engine;
+use http::HeaderMap;
+use ring::hmac;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SIGNATURE_HEADER: &str = "x-bridgerpay-signature";
+/// Max difference between `meta.server_time` and the local time.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookVerifyError {
+    /// No signature header in the request.
+    MissingSignature,
+    /// The signature header is not valid base64.
+    MalformedSignature,
+    /// The signature doesn't match the body, so it was not sent by BridgerPay or was altered.
+    InvalidSignature,
+    /// The signed body has no valid `meta.server_time`.
+    MalformedPayload(String),
+    /// `meta.server_time` is too far from the local time, e.g. a replayed webhook.
+    OutsideTolerance { server_time: u64, now: u64 },
+}
+
+impl fmt::Display for WebhookVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookVerifyError::MissingSignature => {
+                write!(f, "Missing {SIGNATURE_HEADER} header")
+            }
+            WebhookVerifyError::MalformedSignature => {
+                write!(f, "{SIGNATURE_HEADER} header is not valid base64")
+            }
+            WebhookVerifyError::InvalidSignature => write!(f, "Webhook signature doesn't match"),
+            WebhookVerifyError::MalformedPayload(message) => {
+                write!(f, "Invalid webhook payload: {message}")
+            }
+            WebhookVerifyError::OutsideTolerance { server_time, now } => write!(
+                f,
+                "Webhook server_time {server_time} is too far from local time {now}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WebhookVerifyError {}
+
+#[derive(Debug, Clone)]
+pub struct WebhookVerifier {
+    key: hmac::Key,
+    tolerance: Duration,
+}
+
+impl WebhookVerifier {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn verify(&self, raw_body: &[u8], headers: &HeaderMap) -> Result<(), WebhookVerifyError> {
+        self.verify_at(raw_body, headers, SystemTime::now())
+    }
+
+    /// Same as [`Self::verify`] with `now` as the local time.
+    pub fn verify_at(
+        &self,
+        raw_body: &[u8],
+        headers: &HeaderMap,
+        now: SystemTime,
+    ) -> Result<(), WebhookVerifyError> {
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .ok_or(WebhookVerifyError::MissingSignature)?;
+        let signature = general_purpose::STANDARD
+            .decode(signature.as_bytes())
+            .map_err(|_| WebhookVerifyError::MalformedSignature)?;
+
+        // constant-time comparison
+        hmac::verify(&self.key, raw_body, &signature)
+            .map_err(|_| WebhookVerifyError::InvalidSignature)?;
+
+        let meta: WebhookMetaValue = serde_json::from_slice(raw_body)
+            .map_err(|err| WebhookVerifyError::MalformedPayload(err.to_string()))?;
+        let server_time = meta.meta.server_time;
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+
+        if server_time.abs_diff(now) > self.tolerance.as_secs() {
+            return Err(WebhookVerifyError::OutsideTolerance { server_time, now });
+        }
+
+        Ok(())
+    }
+
+    /// Base64 signature of the body, e.g. to send test webhooks.
+    pub fn sign(&self, raw_body: &[u8]) -> String {
+        general_purpose::STANDARD.encode(hmac::sign(&self.key, raw_body))
+    }
+}
+
+/// Checks that the webhook was signed with `secret` and sent within [`DEFAULT_TOLERANCE`].
+/// Call it with the body exactly as received, before deserializing it.
+pub fn verify_webhook(
+    raw_body: &[u8],
+    headers: &HeaderMap,
+    secret: &str,
+) -> Result<(), WebhookVerifyError> {
+    WebhookVerifier::new(secret).verify(raw_body, headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WebhookVerifier, WebhookVerifyError, SIGNATURE_HEADER};
+    use http::HeaderMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn verifies_signature_and_time() {
+        let verifier = WebhookVerifier::new("secret");
+        let body = br#"{"meta":{"server_time":1700000000,"server_timezone":"UTC","api_version":"v2","cashier_session_id":"session-1"}}"#;
+        let now = UNIX_EPOCH + Duration::from_secs(1700000100);
+        let mut headers = HeaderMap::new();
+
+        assert_eq!(
+            verifier.verify_at(body, &headers, now),
+            Err(WebhookVerifyError::MissingSignature)
+        );
+
+        headers.insert(SIGNATURE_HEADER, verifier.sign(body).parse().unwrap());
+        assert_eq!(verifier.verify_at(body, &headers, now), Ok(()));
+
+        let tampered = String::from_utf8_lossy(body).replace("session-1", "session-2");
+        assert_eq!(
+            verifier.verify_at(tampered.as_bytes(), &headers, now),
+            Err(WebhookVerifyError::InvalidSignature)
+        );
+
+        let later = now + Duration::from_secs(300);
+        assert!(matches!(
+            verifier.verify_at(body, &headers, later),
+            Err(WebhookVerifyError::OutsideTolerance { .. })
+        ));
+    }
+}
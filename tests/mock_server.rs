@@ -0,0 +1,219 @@
+#![cfg(feature = "mock-server")]
+
+use bridgerpay_connector::rest::api_client::{CheckoutWidgetType, RestApiClient, RestApiConfig};
+use bridgerpay_connector::rest::errors::Error;
+use bridgerpay_connector::rest::mock_server::{MockBridgerPayServer, MockFailure};
+use bridgerpay_connector::rest::retry::RetryPolicy;
+use bridgerpay_connector::rest::{
+    CashierSessionStatus, CreateCashierSessionRequest, CreatePaymentLinkRequest, PaymentLinkStatus,
+};
+use std::time::Duration;
+
+struct MockApiConfig {
+    url: String,
+}
+
+#[async_trait::async_trait]
+impl RestApiConfig for MockApiConfig {
+    async fn get_api_url(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn get_api_key(&self) -> String {
+        "api-key".to_string()
+    }
+
+    async fn get_timeout(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    async fn get_user_name(&self) -> String {
+        "user".to_string()
+    }
+
+    async fn get_password(&self) -> String {
+        "password".to_string()
+    }
+
+    async fn get_cashier_key(&self) -> String {
+        "cashier-key".to_string()
+    }
+
+    async fn get_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            base_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+}
+
+fn client(server: &MockBridgerPayServer) -> RestApiClient<MockApiConfig> {
+    RestApiClient::new(MockApiConfig {
+        url: server.get_url().to_string(),
+    })
+}
+
+fn session_request() -> CreateCashierSessionRequest {
+    serde_json::from_value(serde_json::json!({
+        "cashier_key": null,
+        "order_id": "order-1",
+        "currency": "USD",
+        "country": "US",
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn generates_checkout_widget() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+
+    let widget = client
+        .generate_checkout_widget(session_request(), CheckoutWidgetType::Regular)
+        .await
+        .unwrap();
+
+    assert!(widget.html.contains(&widget.params.cashier_token));
+    assert_eq!(server.get_requests_to("/v2/auth/login").len(), 1);
+    let sessions = server.get_requests_to("/v2/cashier/session/create/api-key");
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].body.contains("\"cashier_key\":\"cashier-key\""));
+    assert!(sessions[0]
+        .get_header("Authorization")
+        .unwrap()
+        .starts_with("Bearer access-token-"));
+}
+
+#[tokio::test]
+async fn logs_in_again_after_revoked_token() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+
+    client.create_cashier_session(session_request()).await.unwrap();
+    server.revoke_tokens();
+    client.create_cashier_session(session_request()).await.unwrap();
+
+    assert_eq!(server.get_requests_to("/v2/cashier/session/create").len(), 3);
+    assert_eq!(server.get_requests_to("/v2/auth/refresh_token").len(), 1);
+}
+
+#[tokio::test]
+async fn retries_login_on_server_error() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    server.fail_next("/v2/auth/login", MockFailure::Status(500));
+    server.fail_next("/v2/auth/login", MockFailure::Status(503));
+    let client = client(&server);
+
+    client.login().await.unwrap();
+
+    assert_eq!(server.get_requests_to("/v2/auth/login").len(), 3);
+}
+
+#[tokio::test]
+async fn reports_malformed_json_and_api_errors() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+    client.login().await.unwrap();
+
+    server.fail_next("/v2/cashier/session/create", MockFailure::MalformedJson);
+    let error = client
+        .create_cashier_session(session_request())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Deserialize { .. }));
+
+    server.fail_next(
+        "/v2/cashier/session/create",
+        MockFailure::ApiError {
+            code: 1001,
+            message: "Invalid currency".to_string(),
+        },
+    );
+    let error = client
+        .create_cashier_session(session_request())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::ApiError { code: 1001, .. }));
+}
+
+#[tokio::test]
+async fn times_out_slow_responses() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+    client.login().await.unwrap();
+
+    server.fail_next(
+        "/v2/cashier/session/create",
+        MockFailure::Delay(Duration::from_secs(2)),
+    );
+    let error = client
+        .create_cashier_session(session_request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::Timeout { .. }));
+}
+
+#[tokio::test]
+async fn gets_and_cancels_cashier_session() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+    let session = client.create_cashier_session(session_request()).await.unwrap();
+    let token = session.cashier_token;
+
+    server.set_result(
+        "/v2/cashier/session/api-key/",
+        serde_json::json!({ "cashier_token": token, "status": "open", "order_id": "order-1" }),
+    );
+    server.set_result(
+        "/v2/cashier/session/cancel/",
+        serde_json::json!({ "cashier_token": token, "status": "cancelled" }),
+    );
+
+    let status = client.get_cashier_session(&token).await.unwrap();
+    assert_eq!(status.status, CashierSessionStatus::Open);
+    assert!(status.charges.is_empty());
+
+    let status = client.cancel_cashier_session(&token).await.unwrap();
+    assert_eq!(status.status, CashierSessionStatus::Cancelled);
+    let cancels = server.get_requests_to("/v2/cashier/session/cancel/api-key/");
+    assert_eq!(cancels.len(), 1);
+    assert_eq!(cancels[0].method, "POST");
+}
+
+#[tokio::test]
+async fn creates_and_expires_payment_link() {
+    let server = MockBridgerPayServer::start().await.unwrap();
+    let client = client(&server);
+    let link = serde_json::json!({
+        "payment_link_id": "link-1",
+        "url": "https://pay.test/link-1",
+        "status": "active",
+        "single_use": true,
+    });
+    server.set_result("/v2/payment-links/create", link.clone());
+    let request = CreatePaymentLinkRequest::new(session_request())
+        .expires_at(u64::MAX / 2)
+        .single_use(true);
+
+    let resp = client.create_payment_link(request).await.unwrap();
+
+    assert_eq!(resp.url, "https://pay.test/link-1");
+    let body = &server.get_requests_to("/v2/payment-links/create/api-key")[0].body;
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["order_id"], "order-1");
+    assert_eq!(body["cashier_key"], "cashier-key");
+    assert_eq!(body["single_use"], true);
+
+    let mut expired = link;
+    expired["status"] = "expired".into();
+    server.set_result("/v2/payment-links/expire", expired);
+
+    let resp = client.expire_payment_link("link-1").await.unwrap();
+
+    assert_eq!(resp.status, PaymentLinkStatus::Expired);
+    assert_eq!(
+        server.get_requests_to("/v2/payment-links/expire/api-key/link-1").len(),
+        1
+    );
+}